@@ -1,12 +1,17 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use std::fs;
 use std::io::{self, Write};
 use std::path::PathBuf;
+use timely_pass_sdk::calendar::{Calendar, CalendarEntry};
 use timely_pass_sdk::crypto::{generate_random_bytes, Secret};
-use timely_pass_sdk::eval::{EvaluationContext, Verdict};
-use timely_pass_sdk::policy::Policy;
-use timely_pass_sdk::store::{Credential, SecretStore, SecretType};
+use timely_pass_sdk::dsl;
+use timely_pass_sdk::eval::{
+    EvaluationContext, PolicyEvaluation, Transition, Verdict, VerdictInterval, Warning,
+};
+use timely_pass_sdk::policy::{local_to_utc, parse_timezone, DisabledMode, Policy, Severity};
+use timely_pass_sdk::store::{Credential, DefaultMode, PolicyBinding, SecretStore, SecretType};
+use timely_pass_sdk::timesource;
 
 pub(crate) fn prompt_passphrase(confirm: bool) -> Result<Secret> {
     print!("Enter passphrase: ");
@@ -32,6 +37,72 @@ fn prompt_secret() -> Result<Vec<u8>> {
     Ok(secret.into_bytes())
 }
 
+/// Reads a policy definition file (JSON, TOML or the policy language).
+///
+/// The format is taken from the `.json`, `.toml` or `.policy` extension. Other
+/// files are read as JSON if they start with `{`, and otherwise tried as TOML,
+/// then the policy language.
+pub(crate) fn load_policy_file(path: &PathBuf) -> Result<Policy> {
+    let content = fs::read_to_string(path).context("Failed to read policy file")?;
+
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let policy = match extension {
+        Some("policy") => dsl::parse_policy(&content),
+        Some("json") => Policy::from_json(&content),
+        Some("toml") => Policy::from_toml(&content),
+        _ if content.trim_start().starts_with('{') => Policy::from_json(&content),
+        _ => {
+            let toml_err = match Policy::from_toml(&content) {
+                Ok(policy) => return Ok(policy),
+                Err(e) => e,
+            };
+            return dsl::parse_policy(&content).map_err(|dsl_err| {
+                anyhow::anyhow!(
+                    "Failed to parse policy as TOML or the policy language\n\nAs TOML: {}\nAs policy language: {}",
+                    toml_err,
+                    dsl_err
+                )
+            });
+        }
+    };
+    policy.context("Failed to parse policy")
+}
+
+/// Policies that extend another can only be evaluated against a store.
+fn require_standalone(policy: &Policy) -> Result<()> {
+    if let Some(extends) = &policy.extends {
+        anyhow::bail!(
+            "Policy '{}' extends '{}', which is only resolved inside a store.\nAdd it with 'timely-pass policy add' and use 'timely-pass policy simulate --id {}' instead.",
+            policy.id,
+            extends.policy,
+            policy.id
+        );
+    }
+    Ok(())
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .context("Invalid time format (use ISO 8601)")?
+        .with_timezone(&Utc))
+}
+
+/// Prints validation diagnostics and fails if any of them is an error.
+pub(crate) fn check_policy(policy: &Policy) -> Result<()> {
+    let diagnostics = policy.validate();
+    for d in &diagnostics {
+        eprintln!("{}", d);
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        anyhow::bail!("Policy '{}' is invalid ({} error(s))", policy.id, errors);
+    }
+    Ok(())
+}
+
 pub(crate) fn open_store_helper(store_path: &PathBuf, passphrase: &Secret) -> Result<SecretStore> {
     match SecretStore::open(store_path, passphrase) {
         Ok(s) => Ok(s),
@@ -60,10 +131,8 @@ pub(crate) fn open_store_helper(store_path: &PathBuf, passphrase: &Secret) -> Re
                     // General corruption message
                     anyhow::bail!("Store file at {:?} is corrupted or invalid: {}\nPlease delete it and run 'timely-pass init' again.", store_path, bin_err);
                 }
-                timely_pass_sdk::error::Error::Crypto(ref msg) => {
-                    if msg == "Decryption failed" {
-                        anyhow::bail!("Failed to decrypt the store. \n\nCause: Incorrect passphrase or corrupted file.\n\nPlease try again with the correct passphrase.");
-                    }
+                timely_pass_sdk::error::Error::Crypto(ref msg) if msg == "Decryption failed" => {
+                    anyhow::bail!("Failed to decrypt the store. \n\nCause: Incorrect passphrase or corrupted file.\n\nPlease try again with the correct passphrase.");
                 }
                 _ => {}
             }
@@ -97,7 +166,8 @@ pub async fn add(
     store_path: PathBuf,
     id: String,
     type_: String,
-    policy_path: Option<PathBuf>,
+    policy_paths: Vec<PathBuf>,
+    tags: Vec<String>,
     read_secret: bool,
 ) -> Result<()> {
     let passphrase = prompt_passphrase(false)?;
@@ -121,19 +191,17 @@ pub async fn add(
         _ => anyhow::bail!("Invalid secret type. Allowed: password, key, token"),
     };
 
-    let mut cred = Credential::new(id.clone(), secret_type, secret_data);
+    let mut cred = Credential::new_with_clock(id.clone(), secret_type, secret_data, store.clock());
     cred.id = id.clone();
+    cred.tags = tags;
 
-    if let Some(path) = policy_path {
-        let content = fs::read_to_string(&path).context("Failed to read policy file")?;
-        // Parse TOML policy
-        // We need to implement Deserialize for Policy from TOML
-        // Our Policy struct has Deserialize derived, so:
-        let policy: Policy = toml::from_str(&content).context("Failed to parse policy TOML")?;
+    for path in policy_paths {
+        let policy = load_policy_file(&path)?;
+        check_policy(&policy)?;
 
         // Add policy to store
         store.add_policy(policy.clone())?;
-        cred.policy_id = Some(policy.id);
+        cred.policy_ids.push(policy.id);
     }
 
     store.add_credential(cred)?;
@@ -141,46 +209,66 @@ pub async fn add(
     Ok(())
 }
 
-pub async fn get(store_path: PathBuf, id: String) -> Result<()> {
+pub async fn get(store_path: PathBuf, id: String, time_server: Option<String>) -> Result<()> {
+    let time_source = time_server
+        .as_deref()
+        .map(timesource::parse_time_source)
+        .transpose()?;
     let passphrase = prompt_passphrase(false)?;
     let mut store = open_store_helper(&store_path, &passphrase)?;
+    store.set_time_source(time_source);
 
-    let (secret, policy_id, created_at, updated_at, usage_counter) = {
+    let (secret, policies) = {
         let cred = store.get_credential(&id).context("Credential not found")?;
-        (
-            cred.secret.clone(),
-            cred.policy_id.clone(),
-            cred.created_at,
-            cred.updated_at,
-            cred.usage_counter,
-        )
+        (cred.secret.clone(), describe_policies(&store, cred))
     };
 
     // Evaluate policy if present
-    if let Some(pid) = policy_id {
-        if let Some(policy) = store.get_policy(&pid) {
-            let ctx = EvaluationContext {
-                now: Utc::now(),
-                created_at: Some(created_at),
-                last_used_at: Some(updated_at),
-                usage_count: usage_counter,
-            };
-
-            let eval = policy.evaluate(&ctx);
-            match eval.verdict {
-                Verdict::Accept => {}
-                v => {
-                    println!("\n❌ ACCESS DENIED");
-                    println!("Reason: {:?}", v);
-                    println!("Policy ID: {}", pid);
-                    if !eval.details.is_empty() {
-                        println!("\nDetails:");
-                        for (key, val) in eval.details {
-                            println!("  - {}: {}", key, val);
-                        }
+    let now = store.clock().now();
+    if let Some(eval) = store.evaluate_credential(&id, now)? {
+        let transition = store.next_transition(&id, now)?;
+        match eval.verdict {
+            Verdict::Accept => {
+                // Keep stdout to the secret itself so it can be piped.
+                eprintln!("Policies: {}", policies);
+                match &eval.warning {
+                    Some(warning @ Warning::InGracePeriod { .. }) => {
+                        eprintln!("⚠️  WARNING: {}. Rotate this credential now.", warning)
+                    }
+                    Some(warning) => eprintln!("Warning: {}", warning),
+                    None => {}
+                }
+                if let Some(t) = transition {
+                    eprintln!("Note: {}", describe_transition(&t, now));
+                }
+            }
+            v => {
+                println!("\n❌ ACCESS DENIED");
+                match &eval.reason {
+                    Some(reason) => println!("Reason: {}", reason),
+                    None => println!("Reason: {:?}", v),
+                }
+                println!("Policies: {}", policies);
+                if let Some(denied_by) = eval.details.get("policy") {
+                    println!("Denied by: {}", denied_by);
+                }
+                match v {
+                    Verdict::RateLimited { retry_after } => {
+                        println!("Retry after: {}", retry_after)
+                    }
+                    Verdict::Locked { until } => println!("Locked until: {}", until),
+                    _ => {}
+                }
+                if let Some(t) = transition {
+                    println!("Access: {}", describe_transition(&t, now));
+                }
+                if !eval.details.is_empty() {
+                    println!("\nDetails:");
+                    for (key, val) in eval.details {
+                        println!("  - {}: {}", key, val);
                     }
-                    return Ok(());
                 }
+                return Ok(());
             }
         }
     }
@@ -201,16 +289,17 @@ pub async fn get(store_path: PathBuf, id: String) -> Result<()> {
     Ok(())
 }
 
-pub async fn eval(policy_path: PathBuf, time: Option<String>) -> Result<()> {
-    let content = fs::read_to_string(&policy_path).context("Failed to read policy file")?;
-    let policy: Policy = toml::from_str(&content).context("Failed to parse policy TOML")?;
+pub async fn eval(policy_path: PathBuf, time: Option<String>, explain: bool) -> Result<()> {
+    let policy = load_policy_file(&policy_path)?;
+    require_standalone(&policy)?;
+    policy.tz()?;
+    for hook in &policy.hooks {
+        hook.validate()?;
+    }
 
-    let now = if let Some(t) = time {
-        DateTime::parse_from_rfc3339(&t)
-            .context("Invalid time format (use ISO 8601)")?
-            .with_timezone(&Utc)
-    } else {
-        Utc::now()
+    let now = match time {
+        Some(t) => parse_time(&t)?,
+        None => Utc::now(),
     };
 
     let ctx = EvaluationContext {
@@ -219,11 +308,111 @@ pub async fn eval(policy_path: PathBuf, time: Option<String>) -> Result<()> {
         ..Default::default()
     };
 
-    let result = policy.evaluate(&ctx);
-    println!("{:#?}", result);
+    if explain {
+        print_trace(&policy, &policy.explain(&ctx));
+    } else {
+        let result = policy.evaluate(&ctx);
+        println!("{:#?}", result);
+    }
     Ok(())
 }
 
+/// Formats a duration with its two largest units, e.g. "3d 4h" or "12m 5s".
+fn format_remaining(remaining: chrono::Duration) -> String {
+    let secs = remaining.num_seconds().max(0);
+    let units = [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)];
+    let parts: Vec<String> = units
+        .iter()
+        .scan(secs, |left, (suffix, size)| {
+            let value = *left / size;
+            *left %= size;
+            Some((value, suffix))
+        })
+        .skip_while(|(value, _)| *value == 0)
+        .take(2)
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, suffix)| format!("{}{}", value, suffix))
+        .collect();
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// The effective policies of a credential, noting those that come from a tag.
+fn describe_policies(store: &SecretStore, cred: &Credential) -> String {
+    let policies: Vec<String> = store
+        .effective_policies(cred)
+        .into_iter()
+        .map(|(id, binding)| match binding {
+            PolicyBinding::Direct => id,
+            PolicyBinding::Tag(tag) => format!("{} (tag {})", id, tag),
+            PolicyBinding::StoreDefault => format!("{} (default)", id),
+        })
+        .collect();
+    if policies.is_empty() {
+        "-".to_string()
+    } else {
+        policies.join(", ")
+    }
+}
+
+/// Describes an upcoming transition relative to `now`, e.g. "expires in 3d 4h".
+fn describe_transition(transition: &Transition, now: DateTime<Utc>) -> String {
+    let what = match transition.verdict {
+        Verdict::Accept => "valid in",
+        Verdict::Expired => "expires in",
+        _ => "denied in",
+    };
+    format!("{} {}", what, format_remaining(transition.at - now))
+}
+
+/// Renders an explain-mode evaluation as an indented hook tree.
+fn print_trace(policy: &Policy, result: &PolicyEvaluation) {
+    println!("Verdict: {:?}", result.verdict);
+    if let Some(reason) = &result.reason {
+        println!("Reason:  {}", reason);
+    }
+    if let Some(warning) = &result.warning {
+        println!("Warning: {}", warning);
+    }
+    println!("Clock skew: {}s", policy.clock_skew_secs);
+    println!();
+
+    if result.trace.is_empty() {
+        println!("No hooks evaluated.");
+        return;
+    }
+    for entry in &result.trace {
+        let indent = "  ".repeat(entry.path.matches('.').count());
+        let status = if entry.passed { "PASS" } else { "FAIL" };
+        match &entry.reason {
+            Some(reason) => println!(
+                "{}[{}] {} {}: {}",
+                indent, entry.path, status, entry.kind, reason
+            ),
+            None => println!("{}[{}] {} {}", indent, entry.path, status, entry.kind),
+        }
+        for b in &entry.boundaries {
+            if b.at == b.effective {
+                println!("{}    {:<12} {}", indent, b.label, b.at.to_rfc3339());
+            } else {
+                println!(
+                    "{}    {:<12} {} (effective {})",
+                    indent,
+                    b.label,
+                    b.at.to_rfc3339(),
+                    b.effective.to_rfc3339()
+                );
+            }
+        }
+        if let Some(observed) = &entry.observed {
+            println!("{}    {}", indent, observed);
+        }
+    }
+}
+
 pub async fn list(store_path: PathBuf) -> Result<()> {
     let passphrase = prompt_passphrase(false)?;
     let store = open_store_helper(&store_path, &passphrase)?;
@@ -234,18 +423,82 @@ pub async fn list(store_path: PathBuf) -> Result<()> {
         println!("\nHint: Add a credential using:");
         println!("  timely-pass add --id <name> --secret");
     } else {
-        println!("{:<20} {:<20} {:<30}", "ID", "Type", "Created At");
-        println!("{:-<20} {:-<20} {:-<30}", "", "", "");
+        let now = store.clock().now();
+        println!(
+            "{:<20} {:<20} {:<30} {:<20} Policies",
+            "ID", "Type", "Created At", "Validity"
+        );
+        println!(
+            "{:-<20} {:-<20} {:-<30} {:-<20} {:-<20}",
+            "", "", "", "", ""
+        );
         for cred in creds {
+            let validity = match (cred.locked_at(now), store.next_transition(&cred.id, now)) {
+                (Some(until), _) => format!("locked for {}", format_remaining(until - now)),
+                (None, Ok(Some(t))) => describe_transition(&t, now),
+                (None, Ok(None)) => "-".to_string(),
+                (None, Err(_)) => "invalid policy".to_string(),
+            };
             println!(
-                "{:<20} {:<20?} {:<30}",
-                cred.id, cred.secret.type_, cred.created_at
+                "{:<20} {:<20?} {:<30} {:<20} {}",
+                cred.id,
+                cred.secret.type_,
+                cred.created_at,
+                validity,
+                describe_policies(&store, cred)
             );
         }
     }
     Ok(())
 }
 
+pub async fn unlock(store_path: PathBuf, id: String) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    let failures = store
+        .get_credential(&id)
+        .context("Credential not found")?
+        .failed_attempts;
+    store.unlock_credential(&id)?;
+    println!(
+        "Credential '{}' unlocked ({} failed attempt(s) cleared).",
+        id, failures
+    );
+    Ok(())
+}
+
+pub async fn clock_show(store_path: PathBuf) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let store = open_store_helper(&store_path, &passphrase)?;
+
+    let now = store.clock().now();
+    println!("Clock:      {}", now);
+    match store.time_high_water() {
+        Some(high_water) if high_water > now => println!(
+            "High-water: {} ({} ahead of the clock)",
+            high_water,
+            format_remaining(high_water - now)
+        ),
+        Some(high_water) => println!("High-water: {}", high_water),
+        None => println!("High-water: none"),
+    }
+    Ok(())
+}
+
+pub async fn clock_reset(store_path: PathBuf) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    let previous = store.reset_time_high_water()?;
+    let now = store.clock().now();
+    match previous {
+        Some(previous) => println!("High-water mark moved from {} to {}.", previous, now),
+        None => println!("High-water mark set to {}.", now),
+    }
+    Ok(())
+}
+
 pub async fn rotate(store_path: PathBuf, id: String) -> Result<()> {
     let passphrase = prompt_passphrase(false)?;
     let mut store = open_store_helper(&store_path, &passphrase)?;
@@ -260,7 +513,7 @@ pub async fn rotate(store_path: PathBuf, id: String) -> Result<()> {
     // Fetch, modify, insert.
     if let Some(mut cred) = store.get_credential(&id).cloned() {
         cred.secret.data = new_secret_data;
-        cred.updated_at = Utc::now();
+        cred.updated_at = store.clock().now();
         store.add_credential(cred)?;
         println!("Rotated successfully.");
     }
@@ -272,35 +525,167 @@ pub async fn policy_add(store_path: PathBuf, id: Option<String>, file: PathBuf)
     let passphrase = prompt_passphrase(false)?;
     let mut store = open_store_helper(&store_path, &passphrase)?;
 
-    let content = fs::read_to_string(&file).context("Failed to read policy file")?;
-
-    // Try JSON first, then TOML
-    let mut policy: Policy = match serde_json::from_str(&content) {
-        Ok(p) => p,
-        Err(_) => toml::from_str(&content).context("Failed to parse policy as JSON or TOML")?,
-    };
+    let mut policy = load_policy_file(&file)?;
 
     if let Some(new_id) = id {
         policy.id = new_id;
     }
+    check_policy(&policy)?;
 
     store.add_policy(policy.clone())?;
     println!("Policy '{}' added/updated.", policy.id);
     Ok(())
 }
 
-pub async fn policy_get(store_path: PathBuf, id: String) -> Result<()> {
+pub async fn policy_validate(file: PathBuf) -> Result<()> {
+    let policy = load_policy_file(&file)?;
+    check_policy(&policy)?;
+    println!("Policy '{}' is valid.", policy.id);
+    Ok(())
+}
+
+pub async fn policy_simulate(
+    store_path: PathBuf,
+    id: Option<String>,
+    file: Option<PathBuf>,
+    from: String,
+    to: String,
+) -> Result<()> {
+    // Treat the credential as created at the start of the range and never used.
+    let from = parse_time(&from)?;
+    let to = parse_time(&to)?;
+    if from >= to {
+        anyhow::bail!("--from must be before --to");
+    }
+    let mut ctx = EvaluationContext {
+        now: from,
+        created_at: Some(from),
+        ..Default::default()
+    };
+
+    let policy = match (id, file) {
+        (_, Some(file)) => {
+            let policy = load_policy_file(&file)?;
+            check_policy(&policy)?;
+            require_standalone(&policy)?;
+            if !policy.calendar_names().is_empty() {
+                eprintln!("Warning: calendars are only available with --id; blackout hooks will deny access.");
+            }
+            policy
+        }
+        (Some(id), None) => {
+            let passphrase = prompt_passphrase(false)?;
+            let store = open_store_helper(&store_path, &passphrase)?;
+            let policy = store
+                .resolve_policy(&id)?
+                .with_context(|| format!("Policy '{}' not found", id))?;
+            for name in policy.calendar_names() {
+                if let Some(calendar) = store.get_calendar(name) {
+                    ctx.calendars.insert(name.to_string(), calendar.clone());
+                }
+            }
+            policy
+        }
+        (None, None) => anyhow::bail!("Specify --id or --file"),
+    };
+
+    let intervals = policy.simulate(&ctx, to);
+
+    println!("{:<26} {:<26} Verdict", "From", "Until");
+    println!("{:-<26} {:-<26} {:-<30}", "", "", "");
+    for interval in &intervals {
+        println!(
+            "{:<26} {:<26} {:?}",
+            interval.start.format("%Y-%m-%d %H:%M:%S UTC"),
+            interval.end.format("%Y-%m-%d %H:%M:%S UTC"),
+            interval.verdict
+        );
+    }
+    if let Some(last) = intervals.last().filter(|i| i.truncated) {
+        println!(
+            "\nWarning: the timeline is incomplete after {}; simulate a shorter range from there.",
+            last.end.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+    }
+
+    println!();
+    print_timeline(&policy, &intervals, from, to)?;
+    Ok(())
+}
+
+/// Draws one row per local day with one cell per hour: `#` granted for the whole
+/// hour, `+` granted for part of it, `.` denied.
+fn print_timeline(
+    policy: &Policy,
+    intervals: &[VerdictInterval],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<()> {
+    let tz = policy.tz()?;
+    let granted_secs = |start: DateTime<Utc>, end: DateTime<Utc>| -> i64 {
+        intervals
+            .iter()
+            .filter(|i| i.verdict == Verdict::Accept)
+            .map(|i| (i.end.min(end) - i.start.max(start)).num_seconds().max(0))
+            .sum()
+    };
+
+    let header: String = (0..24).step_by(6).map(|h| format!("{:<6}", h)).collect();
+    println!("Timeline ({}, # granted, + partly granted, . denied)", tz);
+    println!("{:<15}{}", "", header);
+
+    let first = from.with_timezone(&tz).date_naive();
+    let last = (to - chrono::Duration::seconds(1))
+        .with_timezone(&tz)
+        .date_naive();
+    for day in first.iter_days().take_while(|d| *d <= last) {
+        let row: String = (0..24)
+            .map(|h| {
+                let hour = day.and_hms_opt(h, 0, 0).expect("valid hour");
+                let start = local_to_utc(&tz, hour).max(from);
+                let end = local_to_utc(&tz, hour + chrono::Duration::hours(1)).min(to);
+                if end <= start {
+                    return ' '; // outside the range or skipped by a DST change
+                }
+                match granted_secs(start, end) {
+                    0 => '.',
+                    g if g >= (end - start).num_seconds() => '#',
+                    _ => '+',
+                }
+            })
+            .collect();
+        println!("{:<15}{}", day.format("%a %Y-%m-%d"), row);
+    }
+    Ok(())
+}
+
+pub async fn policy_get(
+    store_path: PathBuf,
+    id: String,
+    resolved: bool,
+    as_dsl: bool,
+) -> Result<()> {
     let passphrase = prompt_passphrase(false)?;
     let store = open_store_helper(&store_path, &passphrase)?;
 
-    if let Some(policy) = store.get_policy(&id) {
-        println!("{}", serde_json::to_string_pretty(policy)?);
+    let policy = if resolved {
+        store.resolve_policy(&id)?
     } else {
-        anyhow::bail!("Policy '{}' not found", id);
+        store.get_policy(&id).cloned()
+    };
+    match policy {
+        Some(policy) if as_dsl => print!("{}", dsl::format_policy(&policy)),
+        Some(policy) => println!("{}", serde_json::to_string_pretty(&policy)?),
+        None => anyhow::bail!("Policy '{}' not found", id),
     }
     Ok(())
 }
 
+pub async fn policy_schema() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&Policy::json_schema())?);
+    Ok(())
+}
+
 pub async fn policy_list(store_path: PathBuf) -> Result<()> {
     let passphrase = prompt_passphrase(false)?;
     let store = open_store_helper(&store_path, &passphrase)?;
@@ -309,15 +694,158 @@ pub async fn policy_list(store_path: PathBuf) -> Result<()> {
     if policies.is_empty() {
         println!("No policies found.");
     } else {
-        println!("{:<20} {:<10} {:<10}", "ID", "Version", "Hooks");
-        println!("{:-<20} {:-<10} {:-<10}", "", "", "");
+        println!(
+            "{:<20} {:<10} {:<10} {:<20} Tags",
+            "ID", "Version", "Hooks", "Extends"
+        );
+        println!(
+            "{:-<20} {:-<10} {:-<10} {:-<20} {:-<20}",
+            "", "", "", "", ""
+        );
         for p in policies {
-            println!("{:<20} {:<10} {:<10}", p.id, p.version, p.hooks.len());
+            let extends = p.extends.as_ref().map_or("-", |e| e.policy.as_str());
+            let tags = store.tags_bound_to(&p.id);
+            println!(
+                "{:<20} {:<10} {:<10} {:<20} {}",
+                p.id,
+                p.version,
+                p.hooks.len(),
+                extends,
+                if tags.is_empty() {
+                    "-".to_string()
+                } else {
+                    tags.join(", ")
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+pub async fn policy_bind(store_path: PathBuf, id: String, tag: String) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    if store.get_policy(&id).is_none() {
+        anyhow::bail!("Policy '{}' not found", id);
+    }
+    store.bind_policy(&tag, &id)?;
+    println!(
+        "Policy '{}' now applies to credentials tagged '{}'.",
+        id, tag
+    );
+    Ok(())
+}
+
+pub async fn policy_unbind(store_path: PathBuf, id: String, tag: String) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    if !store.tags_bound_to(&id).contains(&tag.as_str()) {
+        anyhow::bail!("Policy '{}' is not bound to tag '{}'", id, tag);
+    }
+    store.unbind_policy(&tag, &id)?;
+    println!("Policy '{}' unbound from tag '{}'.", id, tag);
+    Ok(())
+}
+
+pub async fn policy_history(store_path: PathBuf, id: String) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let store = open_store_helper(&store_path, &passphrase)?;
+
+    let history = store.policy_history(&id);
+    if history.is_empty() {
+        anyhow::bail!("Policy '{}' not found", id);
+    }
+    let current = store.get_policy(&id).map(|p| p.version);
+    println!(
+        "{:<10} {:<26} {:<10} Details",
+        "Version", "Recorded At", "Action"
+    );
+    println!("{:-<10} {:-<26} {:-<10} {:-<40}", "", "", "", "");
+    for revision in history {
+        let marker = if Some(revision.version) == current {
+            "*"
+        } else {
+            ""
+        };
+        println!(
+            "{:<10} {:<26} {:<10} {}",
+            format!("{}{}", revision.version, marker),
+            revision.recorded_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            revision.audit.action,
+            revision.audit.details
+        );
+    }
+    if current.is_some() {
+        println!("\n* current version");
+    }
+    Ok(())
+}
+
+pub async fn policy_diff(
+    store_path: PathBuf,
+    id: String,
+    from: u32,
+    to: Option<u32>,
+) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let store = open_store_helper(&store_path, &passphrase)?;
+
+    let version = |v: u32| {
+        store
+            .get_policy_version(&id, v)
+            .with_context(|| format!("Policy '{}' has no version {}", id, v))
+    };
+    let before = version(from)?;
+    let after = match to {
+        Some(v) => version(v)?,
+        None => store
+            .get_policy(&id)
+            .with_context(|| format!("Policy '{}' not found", id))?,
+    };
+
+    // The version number always differs; only report real changes.
+    let changes: Vec<_> = before
+        .diff(after)
+        .into_iter()
+        .filter(|c| c.path != "version")
+        .collect();
+    if changes.is_empty() {
+        println!(
+            "No changes between versions {} and {}.",
+            from, after.version
+        );
+    }
+    let show =
+        |v: &Option<serde_json::Value>| v.as_ref().map(|v| v.to_string()).unwrap_or_default();
+    for change in changes {
+        match (&change.before, &change.after) {
+            (None, _) => println!("+ {}: {}", change.path, show(&change.after)),
+            (_, None) => println!("- {}: {}", change.path, show(&change.before)),
+            _ => println!(
+                "~ {}: {} -> {}",
+                change.path,
+                show(&change.before),
+                show(&change.after)
+            ),
         }
     }
     Ok(())
 }
 
+pub async fn policy_rollback(store_path: PathBuf, id: String, to: u32) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    let version = store.rollback_policy(&id, to)?;
+    println!(
+        "Policy '{}' rolled back to version {} (now version {}).",
+        id, to, version
+    );
+    Ok(())
+}
+
 pub async fn policy_remove(store_path: PathBuf, id: String) -> Result<()> {
     let passphrase = prompt_passphrase(false)?;
     let mut store = open_store_helper(&store_path, &passphrase)?;
@@ -338,6 +866,7 @@ pub async fn policy_update(
     max_attempts: Option<u32>,
     single_use: bool,
     multi_use: bool,
+    when_disabled: Option<String>,
 ) -> Result<()> {
     let passphrase = prompt_passphrase(false)?;
     let mut store = open_store_helper(&store_path, &passphrase)?;
@@ -353,12 +882,22 @@ pub async fn policy_update(
             updated = true;
         }
 
+        if let Some(mode) = when_disabled {
+            policy.when_disabled = match mode.as_str() {
+                "deny" => DisabledMode::Deny,
+                "bypass" => DisabledMode::Bypass,
+                _ => anyhow::bail!("Invalid disabled mode. Allowed: deny, bypass"),
+            };
+            updated = true;
+        }
+
         if let Some(s) = skew {
             policy.clock_skew_secs = s;
             updated = true;
         }
 
         if let Some(tz) = timezone {
+            parse_timezone(&tz)?;
             policy.timezone = Some(tz);
             updated = true;
         }
@@ -389,6 +928,157 @@ pub async fn policy_update(
     Ok(())
 }
 
+/// Parses a `--date` value: a single day or an inclusive `start..end` range.
+fn parse_calendar_entry(value: &str) -> Result<CalendarEntry> {
+    let parse = |d: &str| {
+        NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d")
+            .with_context(|| format!("Invalid date '{}' (use YYYY-MM-DD)", d))
+    };
+    match value.split_once("..") {
+        Some((start, end)) => Ok(CalendarEntry::range(parse(start)?, parse(end)?)),
+        None => Ok(CalendarEntry::day(parse(value)?)),
+    }
+}
+
+pub async fn calendar_add(
+    store_path: PathBuf,
+    name: String,
+    dates: Vec<String>,
+    label: Option<String>,
+    ics: Option<PathBuf>,
+) -> Result<()> {
+    if dates.is_empty() && ics.is_none() {
+        anyhow::bail!("Specify at least one --date or an --ics file");
+    }
+
+    let mut entries = Vec::new();
+    for date in &dates {
+        let entry = parse_calendar_entry(date)?;
+        entries.push(match &label {
+            Some(label) => entry.with_label(label),
+            None => entry,
+        });
+    }
+    if let Some(path) = ics {
+        let content = fs::read_to_string(&path).context("Failed to read iCalendar file")?;
+        entries.extend(Calendar::from_ics(&name, &content)?.entries);
+    }
+
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    let mut calendar = store
+        .get_calendar(&name)
+        .cloned()
+        .unwrap_or_else(|| Calendar::new(&name));
+    let added = entries.len();
+    calendar.entries.extend(entries);
+    store.add_calendar(calendar)?;
+    println!("Added {} entries to calendar '{}'.", added, name);
+    Ok(())
+}
+
+pub async fn calendar_get(store_path: PathBuf, name: String) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let store = open_store_helper(&store_path, &passphrase)?;
+
+    let calendar = store
+        .get_calendar(&name)
+        .with_context(|| format!("Calendar '{}' not found", name))?;
+    println!("{:<12} {:<12} Label", "Start", "End");
+    println!("{:-<12} {:-<12} {:-<30}", "", "", "");
+    let mut entries: Vec<_> = calendar.entries.iter().collect();
+    entries.sort_by_key(|e| e.start);
+    for e in entries {
+        println!(
+            "{:<12} {:<12} {}",
+            e.start,
+            e.end,
+            e.label.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+pub async fn calendar_list(store_path: PathBuf) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let store = open_store_helper(&store_path, &passphrase)?;
+
+    let calendars = store.list_calendars();
+    if calendars.is_empty() {
+        println!("No calendars found.");
+    } else {
+        println!("{:<20} {:<10}", "Name", "Entries");
+        println!("{:-<20} {:-<10}", "", "");
+        for c in calendars {
+            println!("{:<20} {:<10}", c.name, c.entries.len());
+        }
+    }
+    Ok(())
+}
+
+pub async fn calendar_remove(store_path: PathBuf, name: String) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    store.remove_calendar(&name)?;
+    println!("Calendar '{}' removed.", name);
+    Ok(())
+}
+
+pub async fn default_set(store_path: PathBuf, id: String, mode: String) -> Result<()> {
+    let mode = match mode.as_str() {
+        "fallback" => DefaultMode::Fallback,
+        "floor" => DefaultMode::Floor,
+        _ => anyhow::bail!("Invalid default mode. Allowed: fallback, floor"),
+    };
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    if store.get_policy(&id).is_none() {
+        anyhow::bail!("Policy '{}' not found", id);
+    }
+    store.set_default_policy(&id, mode)?;
+    match mode {
+        DefaultMode::Fallback => println!(
+            "Policy '{}' now applies to credentials without a policy.",
+            id
+        ),
+        DefaultMode::Floor => println!("Policy '{}' now applies to every credential.", id),
+    }
+    Ok(())
+}
+
+pub async fn default_show(store_path: PathBuf) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let store = open_store_helper(&store_path, &passphrase)?;
+
+    match store.default_policy() {
+        Some(default) => {
+            let mode = match default.mode {
+                DefaultMode::Fallback => "fallback",
+                DefaultMode::Floor => "floor",
+            };
+            println!("Default policy: {} ({})", default.policy_id, mode);
+        }
+        None => println!("No default policy set."),
+    }
+    Ok(())
+}
+
+pub async fn default_clear(store_path: PathBuf) -> Result<()> {
+    let passphrase = prompt_passphrase(false)?;
+    let mut store = open_store_helper(&store_path, &passphrase)?;
+
+    if store.default_policy().is_none() {
+        println!("No default policy set.");
+        return Ok(());
+    }
+    store.clear_default_policy()?;
+    println!("Default policy cleared.");
+    Ok(())
+}
+
 pub async fn upgrade(version: Option<String>) -> Result<()> {
     println!("Upgrading timely-pass-cli...");
 
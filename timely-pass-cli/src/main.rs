@@ -42,9 +42,14 @@ enum Commands {
         #[arg(long, default_value = "password")]
         type_: String,
 
-        /// Path to policy file (TOML)
+        /// Path to policy file (JSON, TOML or policy language); repeat to
+        /// require several policies
         #[arg(long)]
-        policy: Option<PathBuf>,
+        policy: Vec<PathBuf>,
+
+        /// Tag the credential, applying any policies bound to the tag; repeatable
+        #[arg(long)]
+        tag: Vec<String>,
 
         /// Provide secret via stdin or prompt
         #[arg(long, action)]
@@ -56,6 +61,11 @@ enum Commands {
         /// Credential ID/Label
         #[arg(long)]
         id: String,
+
+        /// Time server for policies that require trusted time
+        /// (sntp://host:port or signed://host:port#<base64 public key>)
+        #[arg(long, value_name = "URL")]
+        time_server: Option<String>,
     },
 
     /// Evaluate a policy
@@ -67,6 +77,10 @@ enum Commands {
         /// Time to evaluate against (ISO 8601)
         #[arg(long)]
         time: Option<String>,
+
+        /// Check every hook and print a per-hook trace
+        #[arg(long)]
+        explain: bool,
     },
 
     /// Rotate a credential
@@ -86,12 +100,37 @@ enum Commands {
         id: String,
     },
 
+    /// Clear a credential's lock and failed-attempt count
+    Unlock {
+        /// Credential ID
+        #[arg(long)]
+        id: String,
+    },
+
     /// Manage policies
     Policy {
         #[command(subcommand)]
         command: PolicyCommands,
     },
 
+    /// Manage blackout calendars
+    Calendar {
+        #[command(subcommand)]
+        command: CalendarCommands,
+    },
+
+    /// Manage the store's default policy
+    Default {
+        #[command(subcommand)]
+        command: DefaultCommands,
+    },
+
+    /// Inspect or reset the store's clock rollback protection
+    Clock {
+        #[command(subcommand)]
+        command: ClockCommands,
+    },
+
     /// Upgrade the CLI
     Upgrade {
         /// Specific version to upgrade to
@@ -108,21 +147,113 @@ enum PolicyCommands {
         #[arg(long)]
         id: Option<String>,
 
-        /// Path to policy definition file (JSON)
+        /// Path to policy definition file (JSON, TOML or policy language)
+        #[arg(long)]
+        file: PathBuf,
+    },
+
+    /// Check a policy file for mistakes without adding it
+    Validate {
+        /// Path to policy definition file (JSON, TOML or policy language)
         #[arg(long)]
         file: PathBuf,
     },
 
+    /// Show how a policy's verdict changes over a time range
+    Simulate {
+        /// ID of a stored policy
+        #[arg(long, conflicts_with = "file", required_unless_present = "file")]
+        id: Option<String>,
+
+        /// Path to policy definition file (JSON, TOML or policy language)
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// Start of the range (ISO 8601)
+        #[arg(long)]
+        from: String,
+
+        /// End of the range (ISO 8601)
+        #[arg(long)]
+        to: String,
+    },
+
+    /// Print the JSON Schema for policy files
+    Schema,
+
     /// Get policy details
     Get {
         /// Policy ID
         #[arg(long)]
         id: String,
+
+        /// Show the policy with inherited hooks and limits applied
+        #[arg(long)]
+        resolved: bool,
+
+        /// Print the policy in the policy language instead of JSON
+        #[arg(long)]
+        dsl: bool,
     },
 
     /// List all policies
     List,
 
+    /// List the recorded versions of a policy
+    History {
+        /// Policy ID
+        #[arg(long)]
+        id: String,
+    },
+
+    /// Show what changed between two versions of a policy
+    Diff {
+        /// Policy ID
+        #[arg(long)]
+        id: String,
+
+        /// Older version
+        #[arg(long)]
+        from: u32,
+
+        /// Newer version (defaults to the current version)
+        #[arg(long)]
+        to: Option<u32>,
+    },
+
+    /// Restore an earlier version of a policy as a new version
+    Rollback {
+        /// Policy ID
+        #[arg(long)]
+        id: String,
+
+        /// Version to restore
+        #[arg(long)]
+        to: u32,
+    },
+
+    /// Apply a policy to every credential with a tag
+    Bind {
+        /// Policy ID
+        #[arg(long)]
+        id: String,
+
+        /// Credential tag
+        #[arg(long)]
+        tag: String,
+    },
+
+    /// Stop applying a policy to credentials with a tag
+    Unbind {
+        /// Policy ID
+        #[arg(long)]
+        id: String,
+
+        /// Credential tag
+        #[arg(long)]
+        tag: String,
+    },
+
     /// Remove a policy
     Remove {
         /// Policy ID
@@ -163,6 +294,78 @@ enum PolicyCommands {
         /// Unset single use
         #[arg(long, group = "single_use_state")]
         multi_use: bool,
+
+        /// Behavior while disabled (deny, bypass)
+        #[arg(long)]
+        when_disabled: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DefaultCommands {
+    /// Set the default policy
+    Set {
+        /// Policy ID
+        #[arg(long)]
+        id: String,
+
+        /// fallback: only credentials without a policy; floor: every credential
+        #[arg(long, default_value = "fallback")]
+        mode: String,
+    },
+
+    /// Show the default policy
+    Show,
+
+    /// Remove the default policy
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum ClockCommands {
+    /// Show the clock reading and the latest time the store has seen
+    Show,
+
+    /// Move the latest time the store has seen back to the current clock reading
+    Reset,
+}
+
+#[derive(Subcommand)]
+enum CalendarCommands {
+    /// Add entries to a calendar, creating it if needed
+    Add {
+        /// Calendar name
+        #[arg(long)]
+        name: String,
+
+        /// Blackout day (YYYY-MM-DD) or inclusive range (YYYY-MM-DD..YYYY-MM-DD); repeatable
+        #[arg(long)]
+        date: Vec<String>,
+
+        /// Label for the entries given with --date
+        #[arg(long)]
+        label: Option<String>,
+
+        /// Import events from an iCalendar (.ics) file
+        #[arg(long)]
+        ics: Option<PathBuf>,
+    },
+
+    /// Show a calendar's entries
+    Get {
+        /// Calendar name
+        #[arg(long)]
+        name: String,
+    },
+
+    /// List all calendars
+    List,
+
+    /// Remove a calendar
+    Remove {
+        /// Calendar name
+        #[arg(long)]
+        name: String,
     },
 }
 
@@ -176,17 +379,41 @@ async fn main() -> anyhow::Result<()> {
             id,
             type_,
             policy,
+            tag,
             secret,
-        } => commands::add(cli.store, id, type_, policy, secret).await?,
-        Commands::Get { id } => commands::get(cli.store, id).await?,
-        Commands::Eval { policy, time } => commands::eval(policy, time).await?,
+        } => commands::add(cli.store, id, type_, policy, tag, secret).await?,
+        Commands::Get { id, time_server } => commands::get(cli.store, id, time_server).await?,
+        Commands::Eval {
+            policy,
+            time,
+            explain,
+        } => commands::eval(policy, time, explain).await?,
         Commands::Rotate { id } => commands::rotate(cli.store, id).await?,
         Commands::List => commands::list(cli.store).await?,
         Commands::Remove { id } => commands::remove(cli.store, id).await?,
+        Commands::Unlock { id } => commands::unlock(cli.store, id).await?,
         Commands::Policy { command } => match command {
             PolicyCommands::Add { id, file } => commands::policy_add(cli.store, id, file).await?,
-            PolicyCommands::Get { id } => commands::policy_get(cli.store, id).await?,
+            PolicyCommands::Validate { file } => commands::policy_validate(file).await?,
+            PolicyCommands::Simulate { id, file, from, to } => {
+                commands::policy_simulate(cli.store, id, file, from, to).await?
+            }
+            PolicyCommands::Get { id, resolved, dsl } => {
+                commands::policy_get(cli.store, id, resolved, dsl).await?
+            }
+            PolicyCommands::Schema => commands::policy_schema().await?,
             PolicyCommands::List => commands::policy_list(cli.store).await?,
+            PolicyCommands::History { id } => commands::policy_history(cli.store, id).await?,
+            PolicyCommands::Diff { id, from, to } => {
+                commands::policy_diff(cli.store, id, from, to).await?
+            }
+            PolicyCommands::Rollback { id, to } => {
+                commands::policy_rollback(cli.store, id, to).await?
+            }
+            PolicyCommands::Bind { id, tag } => commands::policy_bind(cli.store, id, tag).await?,
+            PolicyCommands::Unbind { id, tag } => {
+                commands::policy_unbind(cli.store, id, tag).await?
+            }
             PolicyCommands::Remove { id } => commands::policy_remove(cli.store, id).await?,
             PolicyCommands::Update {
                 id,
@@ -197,6 +424,7 @@ async fn main() -> anyhow::Result<()> {
                 max_attempts,
                 single_use,
                 multi_use,
+                when_disabled,
             } => {
                 commands::policy_update(
                     cli.store,
@@ -208,10 +436,31 @@ async fn main() -> anyhow::Result<()> {
                     max_attempts,
                     single_use,
                     multi_use,
+                    when_disabled,
                 )
                 .await?
             }
         },
+        Commands::Calendar { command } => match command {
+            CalendarCommands::Add {
+                name,
+                date,
+                label,
+                ics,
+            } => commands::calendar_add(cli.store, name, date, label, ics).await?,
+            CalendarCommands::Get { name } => commands::calendar_get(cli.store, name).await?,
+            CalendarCommands::List => commands::calendar_list(cli.store).await?,
+            CalendarCommands::Remove { name } => commands::calendar_remove(cli.store, name).await?,
+        },
+        Commands::Default { command } => match command {
+            DefaultCommands::Set { id, mode } => commands::default_set(cli.store, id, mode).await?,
+            DefaultCommands::Show => commands::default_show(cli.store).await?,
+            DefaultCommands::Clear => commands::default_clear(cli.store).await?,
+        },
+        Commands::Clock { command } => match command {
+            ClockCommands::Show => commands::clock_show(cli.store).await?,
+            ClockCommands::Reset => commands::clock_reset(cli.store).await?,
+        },
         Commands::Upgrade { version } => commands::upgrade(version).await?,
     }
 
@@ -1,5 +1,4 @@
 use chrono::Utc;
-use serde_json;
 use timely_pass_sdk::policy::{Hook, Period, Policy};
 use timely_pass_sdk::store::{Credential, SecretType};
 
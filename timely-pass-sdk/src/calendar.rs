@@ -0,0 +1,190 @@
+use crate::error::{Error, Result};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+/// A named list of blackout days, such as public holidays or a change freeze.
+///
+/// Calendars live in the store and are referenced by name from `blackout`
+/// hooks, so many policies can share one holiday list.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Calendar {
+    pub name: String,
+    pub entries: Vec<CalendarEntry>,
+}
+
+/// One or more whole days, interpreted in the timezone of the policy that uses them.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CalendarEntry {
+    pub start: NaiveDate,
+    pub end: NaiveDate, // inclusive
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl CalendarEntry {
+    pub fn day(date: NaiveDate) -> Self {
+        Self::range(date, date)
+    }
+
+    pub fn range(start: NaiveDate, end: NaiveDate) -> Self {
+        Self {
+            start,
+            end,
+            label: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+}
+
+impl Calendar {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add_entry(mut self, entry: CalendarEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Returns the entry covering `date`, if any.
+    pub fn entry_on(&self, date: NaiveDate) -> Option<&CalendarEntry> {
+        self.entries.iter().find(|e| e.contains(date))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(Error::InvalidCalendar("name must not be empty".to_string()));
+        }
+        for entry in &self.entries {
+            if entry.end < entry.start {
+                return Err(Error::InvalidCalendar(format!(
+                    "entry ends on {} before it starts on {}",
+                    entry.end, entry.start
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the events of an iCalendar (`.ics`) file as calendar entries.
+    ///
+    /// Only the dates of each `VEVENT` are kept. Recurring events (`RRULE`) are
+    /// rejected rather than silently imported as a single day.
+    pub fn from_ics(name: impl Into<String>, ics: &str) -> Result<Self> {
+        let mut calendar = Calendar::new(name);
+        let mut event: Option<IcsEvent> = None;
+
+        for line in unfold(ics) {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let property = key.split(';').next().unwrap_or_default().to_uppercase();
+            match (property.as_str(), event.as_mut()) {
+                ("BEGIN", _) if value.eq_ignore_ascii_case("VEVENT") => {
+                    event = Some(IcsEvent::default());
+                }
+                ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                    let finished = event.take().expect("inside an event");
+                    calendar.entries.push(finished.into_entry()?);
+                }
+                ("DTSTART", Some(e)) => e.start = Some(parse_ics_date(value)?),
+                ("DTEND", Some(e)) => e.end = Some(parse_ics_date(value)?),
+                ("SUMMARY", Some(e)) => e.summary = Some(unescape(value)),
+                ("RRULE", Some(e)) => e.recurring = true,
+                _ => {}
+            }
+        }
+
+        if calendar.entries.is_empty() {
+            return Err(Error::InvalidCalendar(
+                "no events found in iCalendar data".to_string(),
+            ));
+        }
+        Ok(calendar)
+    }
+}
+
+/// A date or date-time property value from an `.ics` file.
+struct IcsDate {
+    date: NaiveDate,
+    midnight: bool, // a date value, or a date-time at 00:00:00
+}
+
+#[derive(Default)]
+struct IcsEvent {
+    start: Option<IcsDate>,
+    end: Option<IcsDate>,
+    summary: Option<String>,
+    recurring: bool,
+}
+
+impl IcsEvent {
+    fn into_entry(self) -> Result<CalendarEntry> {
+        let label = self.summary.unwrap_or_else(|| "(untitled)".to_string());
+        if self.recurring {
+            return Err(Error::InvalidCalendar(format!(
+                "event '{}' repeats (RRULE), which is not supported",
+                label
+            )));
+        }
+        let start = self
+            .start
+            .ok_or_else(|| Error::InvalidCalendar(format!("event '{}' has no DTSTART", label)))?;
+        // DTEND is exclusive, so an event ending at midnight ends the day before.
+        let end = match self.end {
+            Some(end) if end.midnight => end.date - Duration::days(1),
+            Some(end) => end.date,
+            None => start.date,
+        };
+        Ok(CalendarEntry::range(start.date, end.max(start.date)).with_label(label))
+    }
+}
+
+/// Joins folded content lines (continuations start with a space or tab).
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in ics.lines() {
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(raw.trim_end_matches('\r').to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_ics_date(value: &str) -> Result<IcsDate> {
+    let value = value.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Ok(IcsDate {
+            date,
+            midnight: true,
+        });
+    }
+    let local = value.trim_end_matches('Z');
+    let dt = NaiveDateTime::parse_from_str(local, "%Y%m%dT%H%M%S")
+        .map_err(|_| Error::InvalidCalendar(format!("invalid iCalendar date '{}'", value)))?;
+    Ok(IcsDate {
+        date: dt.date(),
+        midnight: dt.time() == chrono::NaiveTime::MIN,
+    })
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
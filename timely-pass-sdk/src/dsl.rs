@@ -0,0 +1,983 @@
+//! A compact text syntax for policies.
+//!
+//! ```text
+//! policy office-hours:
+//!   within 2026-01-01..2026-12-31
+//!   and weekdays 09:00-17:00 Europe/Berlin
+//!   and max 3 uses
+//! ```
+//!
+//! Clauses joined by `and` at the top level become the policy's hooks, or set
+//! one of its fields (`timezone`, `skew`, `max N uses`, `single use`,
+//! `lock after N failures for D`, `require trusted time`,
+//! `warn D before expiry`, `grace D`, `disabled`, `bypass when disabled`,
+//! `version`, `extends`). Rules can be grouped with parentheses and combined
+//! with `and`, `or` and `not`; mixing `and` and `or` in one group requires
+//! parentheses. [`format_policy`] prints any [`Policy`] in this syntax, and
+//! [`parse_policy`] reads it back unchanged.
+//!
+//! Dates without a time are wall-clock times in the policy timezone: midnight
+//! at the start of the day, or 23:59:59 when they end a range. Times with a `Z`
+//! or offset, such as `2026-01-01T09:00:00Z`, are fixed instants.
+
+use crate::error::{Error, Result};
+use crate::policy::{
+    Backoff, DisabledMode, Extends, Hook, HookMerge, Lockout, Period, Policy, Window,
+};
+use chrono::{
+    DateTime, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Timelike, Utc, Weekday,
+};
+
+const WEEKDAYS: [Weekday; 5] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+const WEEKENDS: [Weekday; 2] = [Weekday::Sat, Weekday::Sun];
+const DAILY: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Parses a policy written in the policy language.
+///
+/// Without a `policy <id>:` header the policy gets a random id, as with
+/// [`Policy::default`].
+pub fn parse_policy(source: &str) -> Result<Policy> {
+    let tokens = tokenize(source)?;
+    Parser::new(tokens).policy()
+}
+
+/// Prints a policy in the policy language, one clause per line.
+pub fn format_policy(policy: &Policy) -> String {
+    let mut clauses: Vec<String> = policy.hooks.iter().map(format_hook).collect();
+
+    if let Some(extends) = &policy.extends {
+        let mut options = Vec::new();
+        if extends.hooks == HookMerge::Replace {
+            options.push("replace".to_string());
+        }
+        if let Some(skew) = extends.clock_skew_secs {
+            options.push(format!("skew {}", format_duration(skew)));
+        }
+        if let Some(max) = extends.max_attempts {
+            options.push(format!("max {} uses", max));
+        }
+        let mut clause = format!("extends {}", quote(&extends.policy));
+        if !options.is_empty() {
+            clause.push_str(&format!(" ({})", options.join(", ")));
+        }
+        clauses.push(clause);
+    }
+    if let Some(timezone) = &policy.timezone {
+        clauses.push(format!("timezone {}", quote(timezone)));
+    }
+    if policy.clock_skew_secs != Policy::default().clock_skew_secs {
+        clauses.push(format!("skew {}", format_duration(policy.clock_skew_secs)));
+    }
+    if let Some(max) = policy.max_attempts {
+        clauses.push(format!("max {} uses", max));
+    }
+    if policy.single_use {
+        clauses.push("single use".to_string());
+    }
+    if let Some(lockout) = &policy.lockout {
+        let mut clause = format!(
+            "lock after {} failures for {}",
+            lockout.max_failures,
+            format_duration(lockout.cooldown_secs)
+        );
+        if let Backoff::Exponential { max_secs } = lockout.backoff {
+            clause.push_str(&format!(" doubling up to {}", format_duration(max_secs)));
+        }
+        clauses.push(clause);
+    }
+    if policy.require_trusted_time {
+        clauses.push("require trusted time".to_string());
+    }
+    if let Some(warn) = policy.warn_before_secs {
+        clauses.push(format!("warn {} before expiry", format_duration(warn)));
+    }
+    if let Some(grace) = policy.grace_period_secs {
+        clauses.push(format!("grace {}", format_duration(grace)));
+    }
+    if !policy.enabled {
+        clauses.push("disabled".to_string());
+    }
+    if policy.when_disabled == DisabledMode::Bypass {
+        clauses.push("bypass when disabled".to_string());
+    }
+    if policy.version != 1 {
+        clauses.push(format!("version {}", policy.version));
+    }
+
+    let mut out = format!("policy {}:\n", quote(&policy.id));
+    for (i, clause) in clauses.iter().enumerate() {
+        let joiner = if i == 0 { "" } else { "and " };
+        out.push_str(&format!("  {}{}\n", joiner, clause));
+    }
+    out
+}
+
+/// Prints a single hook, parenthesizing groups so the result parses back as one rule.
+pub fn format_hook(hook: &Hook) -> String {
+    match hook {
+        Hook::OnlyBefore { period } => format!("before {}", format_period(period)),
+        Hook::OnlyAfter { period } => format!("after {}", format_period(period)),
+        Hook::OnlyWithin { period } => format!("within {}", format_period(period)),
+        Hook::OnlyFor { duration_secs } => format!("for {}", format_duration(*duration_secs)),
+        Hook::IdleTimeout { idle_secs } => format!("idle {}", format_duration(*idle_secs)),
+        Hook::RateLimit {
+            max_uses,
+            window_secs,
+        } => format!(
+            "max {} uses per {}",
+            max_uses,
+            format_duration(*window_secs)
+        ),
+        Hook::OnlyDuring { windows } if windows.is_empty() => "during none".to_string(),
+        Hook::OnlyDuring { windows } => windows
+            .iter()
+            .map(format_window)
+            .collect::<Vec<_>>()
+            .join(", "),
+        Hook::OnlyAt { cron, window_secs } => {
+            format!(
+                "at {} for {}",
+                quote_always(cron),
+                format_duration(*window_secs)
+            )
+        }
+        Hook::Blackout { calendar } => format!("blackout {}", quote(calendar)),
+        Hook::AllOf { hooks } => format_group(hooks, "all", "and"),
+        Hook::AnyOf { hooks } => format_group(hooks, "any", "or"),
+        Hook::Not { hook } => format!("not {}", format_hook(hook)),
+    }
+}
+
+fn format_group(hooks: &[Hook], name: &str, joiner: &str) -> String {
+    match hooks {
+        [] => format!("{}()", name),
+        [only] => format!("{}({})", name, format_hook(only)),
+        _ => {
+            let parts: Vec<String> = hooks.iter().map(format_hook).collect();
+            format!("({})", parts.join(&format!(" {} ", joiner)))
+        }
+    }
+}
+
+fn format_window(window: &Window) -> String {
+    let days = if window.days == WEEKDAYS {
+        "weekdays".to_string()
+    } else if window.days == WEEKENDS {
+        "weekends".to_string()
+    } else if window.days == DAILY {
+        "daily".to_string()
+    } else if window.days.is_empty() {
+        "none".to_string()
+    } else {
+        window
+            .days
+            .iter()
+            .map(|d| d.to_string().to_lowercase())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    format!(
+        "{} {}-{}",
+        days,
+        format_time(window.start),
+        format_time(window.end)
+    )
+}
+
+fn format_time(time: NaiveTime) -> String {
+    if time.nanosecond() != 0 {
+        time.format("%H:%M:%S%.f").to_string()
+    } else if time.second() != 0 {
+        time.format("%H:%M:%S").to_string()
+    } else {
+        time.format("%H:%M").to_string()
+    }
+}
+
+fn format_period(period: &Period) -> String {
+    match period {
+        Period::Instant { value } => format_instant(value),
+        Period::Range { start, end } => {
+            format!("{}..{}", format_instant(start), format_instant(end))
+        }
+        Period::Duration { seconds } => format_duration(*seconds),
+        Period::LocalInstant { value } => format_local(*value, NaiveTime::MIN),
+        Period::LocalRange { start, end } => format!(
+            "{}..{}",
+            format_local(*start, NaiveTime::MIN),
+            format_local(*end, end_of_day())
+        ),
+    }
+}
+
+fn format_instant(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Prints a wall-clock time as a bare date when it falls on `date_time`.
+fn format_local(value: NaiveDateTime, date_time: NaiveTime) -> String {
+    if value.time() == date_time {
+        return value.date().format("%Y-%m-%d").to_string();
+    }
+    format!(
+        "{}T{}",
+        value.date().format("%Y-%m-%d"),
+        format_time(value.time())
+    )
+}
+
+/// Prints seconds as `1d2h3m4s`, leaving out zero units.
+pub fn format_duration(seconds: u64) -> String {
+    if seconds == 0 {
+        return "0s".to_string();
+    }
+    let mut out = String::new();
+    let mut rest = seconds;
+    for (unit, size) in [('d', 86_400), ('h', 3_600), ('m', 60), ('s', 1)] {
+        if rest >= size {
+            out.push_str(&format!("{}{}", rest / size, unit));
+            rest %= size;
+        }
+    }
+    out
+}
+
+fn end_of_day() -> NaiveTime {
+    NaiveTime::from_hms_opt(23, 59, 59).expect("valid time")
+}
+
+/// Writes `value` bare when it reads back as a single word, and quoted otherwise.
+fn quote(value: &str) -> String {
+    let bare = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./+".contains(c))
+        && !is_keyword(value);
+    if bare {
+        value.to_string()
+    } else {
+        quote_always(value)
+    }
+}
+
+fn quote_always(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(word.to_lowercase().as_str(), "and" | "or" | "not")
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Word(String),
+    Str(String),
+    LParen,
+    RParen,
+    Comma,
+    Colon,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    tok: Tok,
+    line: usize,
+    column: usize,
+}
+
+fn syntax_error(line: usize, column: usize, message: impl Into<String>) -> Error {
+    Error::PolicySyntax {
+        line,
+        column,
+        message: message.into(),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+
+    while let Some(&c) = chars.peek() {
+        let (start_line, start_column) = (line, column);
+        let mut push = |tok| {
+            tokens.push(Token {
+                tok,
+                line: start_line,
+                column: start_column,
+            })
+        };
+        match c {
+            '\n' => {
+                chars.next();
+                line += 1;
+                column = 1;
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+                column += 1;
+            }
+            '#' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                column += 1;
+                push(match c {
+                    '(' => Tok::LParen,
+                    ')' => Tok::RParen,
+                    _ => Tok::Comma,
+                });
+            }
+            '"' => {
+                chars.next();
+                column += 1;
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => {
+                                value.push(escaped);
+                                column += 1;
+                            }
+                            None => break,
+                        },
+                        Some('\n') | None => {
+                            return Err(syntax_error(
+                                start_line,
+                                start_column,
+                                "unterminated string",
+                            ))
+                        }
+                        Some(other) => value.push(other),
+                    }
+                    column += 1;
+                }
+                column += 1;
+                push(Tok::Str(value));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "(),\"#".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                    column += 1;
+                }
+                // A trailing colon ends the `policy <id>:` header.
+                match word.strip_suffix(':') {
+                    Some("") => push(Tok::Colon),
+                    Some(rest) => {
+                        push(Tok::Word(rest.to_string()));
+                        tokens.push(Token {
+                            tok: Tok::Colon,
+                            line,
+                            column: column - 1,
+                        });
+                    }
+                    None => push(Tok::Word(word)),
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// How the rules of one group are joined.
+#[derive(Clone, Copy, PartialEq)]
+enum Joiner {
+    And,
+    Or,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    policy: Policy,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            policy: Policy {
+                timezone: None,
+                ..Policy::default()
+            },
+        }
+    }
+
+    fn policy(mut self) -> Result<Policy> {
+        if self.peek_word_at(0).as_deref() == Some("policy")
+            && self.tokens.get(self.pos + 2).map(|t| &t.tok) == Some(&Tok::Colon)
+        {
+            self.pos += 1;
+            self.policy.id = self.name("a policy id")?;
+            self.pos += 1;
+        }
+
+        let mut hooks = Vec::new();
+        let mut joiner = None;
+        let mut has_settings = false;
+        while self.pos < self.tokens.len() {
+            if self.setting()? {
+                has_settings = true;
+            } else {
+                hooks.push(self.rule()?);
+            }
+            match self.joiner(&mut joiner)? {
+                true if self.pos == self.tokens.len() => {
+                    return Err(self.error_before("expected a rule or setting after this"))
+                }
+                true => continue,
+                false if self.pos < self.tokens.len() => {
+                    return Err(self.error("expected 'and', 'or' or the end of the policy"))
+                }
+                false => break,
+            }
+        }
+
+        if joiner == Some(Joiner::Or) {
+            if has_settings {
+                return Err(self.error("settings must be joined to the rules with 'and'"));
+            }
+            hooks = vec![Hook::any_of(hooks)];
+        }
+        self.policy.hooks = hooks;
+        Ok(self.policy)
+    }
+
+    /// Consumes an `and`/`or` joiner, rejecting a mix of the two in one group.
+    fn joiner(&mut self, joiner: &mut Option<Joiner>) -> Result<bool> {
+        let next = match self.peek_word_at(0).as_deref() {
+            Some("and") => Joiner::And,
+            Some("or") => Joiner::Or,
+            _ => return Ok(false),
+        };
+        if joiner.is_some_and(|j| j != next) {
+            return Err(self.error("use parentheses when mixing 'and' and 'or'"));
+        }
+        *joiner = Some(next);
+        self.pos += 1;
+        Ok(true)
+    }
+
+    /// Parses a policy-level setting if one starts here.
+    fn setting(&mut self) -> Result<bool> {
+        let Some(word) = self.peek_word_at(0) else {
+            return Ok(false);
+        };
+        match word.as_str() {
+            "timezone" => {
+                self.pos += 1;
+                let timezone = self.name("a timezone")?;
+                self.set_timezone(timezone)?;
+            }
+            "skew" => {
+                self.pos += 1;
+                self.policy.clock_skew_secs = self.duration()?;
+            }
+            "max" if self.peek_word_at(3).as_deref() != Some("per") => {
+                self.pos += 1;
+                let max = self.number()?;
+                self.expect("uses")?;
+                self.policy.max_attempts = Some(max);
+            }
+            "single" => {
+                self.pos += 1;
+                self.expect("use")?;
+                self.policy.single_use = true;
+            }
+            "lock" => {
+                self.pos += 1;
+                self.expect("after")?;
+                let max_failures = self.number()?;
+                self.expect("failures")?;
+                self.expect("for")?;
+                let cooldown_secs = self.duration()?;
+                let backoff = if self.peek_word_at(0).as_deref() == Some("doubling") {
+                    self.pos += 1;
+                    self.expect("up")?;
+                    self.expect("to")?;
+                    Backoff::Exponential {
+                        max_secs: self.duration()?,
+                    }
+                } else {
+                    Backoff::Fixed
+                };
+                self.policy.lockout = Some(Lockout {
+                    max_failures,
+                    cooldown_secs,
+                    backoff,
+                });
+            }
+            "require" => {
+                self.pos += 1;
+                self.expect("trusted")?;
+                self.expect("time")?;
+                self.policy.require_trusted_time = true;
+            }
+            "warn" => {
+                self.pos += 1;
+                let warn = self.duration()?;
+                self.expect("before")?;
+                self.expect("expiry")?;
+                self.policy.warn_before_secs = Some(warn);
+            }
+            "grace" => {
+                self.pos += 1;
+                self.policy.grace_period_secs = Some(self.duration()?);
+            }
+            "disabled" => {
+                self.pos += 1;
+                self.policy.enabled = false;
+            }
+            "bypass" => {
+                self.pos += 1;
+                self.expect("when")?;
+                self.expect("disabled")?;
+                self.policy.when_disabled = DisabledMode::Bypass;
+            }
+            "version" => {
+                self.pos += 1;
+                self.policy.version = self.number()?;
+            }
+            "extends" => {
+                self.pos += 1;
+                let extends = self.extends()?;
+                self.policy.extends = Some(extends);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn extends(&mut self) -> Result<Extends> {
+        let mut extends = Extends {
+            policy: self.name("a base policy id")?,
+            hooks: HookMerge::Append,
+            clock_skew_secs: None,
+            max_attempts: None,
+        };
+        if self.peek() != Some(&Tok::LParen) {
+            return Ok(extends);
+        }
+        self.pos += 1;
+        loop {
+            match self.peek_word_at(0).as_deref() {
+                Some("replace") => {
+                    self.pos += 1;
+                    extends.hooks = HookMerge::Replace;
+                }
+                Some("append") => {
+                    self.pos += 1;
+                    extends.hooks = HookMerge::Append;
+                }
+                Some("skew") => {
+                    self.pos += 1;
+                    extends.clock_skew_secs = Some(self.duration()?);
+                }
+                Some("max") => {
+                    self.pos += 1;
+                    extends.max_attempts = Some(self.number()?);
+                    self.expect("uses")?;
+                }
+                _ => return Err(self.error("expected 'replace', 'append', 'skew' or 'max'")),
+            }
+            match self.next_tok() {
+                Some(Tok::Comma) => continue,
+                Some(Tok::RParen) => return Ok(extends),
+                _ => return Err(self.error_before("expected ',' or ')'")),
+            }
+        }
+    }
+
+    /// Parses one rule: a hook, `not <rule>`, or a parenthesized group.
+    fn rule(&mut self) -> Result<Hook> {
+        if self.peek() == Some(&Tok::LParen) {
+            self.pos += 1;
+            let hook = self.group()?;
+            self.close()?;
+            return Ok(hook);
+        }
+        let Some(word) = self.peek_word_at(0) else {
+            return Err(self.error("expected a rule"));
+        };
+        self.pos += 1;
+        let hook = match word.as_str() {
+            "not" => Hook::not(self.rule()?),
+            "all" | "any" => {
+                if self.next_tok() != Some(Tok::LParen) {
+                    return Err(self.error_before(format!("expected '(' after '{}'", word)));
+                }
+                let hooks = if self.peek() == Some(&Tok::RParen) {
+                    Vec::new()
+                } else {
+                    vec![self.rule()?]
+                };
+                self.close()?;
+                if word == "all" {
+                    Hook::all_of(hooks)
+                } else {
+                    Hook::any_of(hooks)
+                }
+            }
+            "before" => Hook::OnlyBefore {
+                period: self.period()?,
+            },
+            "after" => Hook::OnlyAfter {
+                period: self.period()?,
+            },
+            "within" => Hook::OnlyWithin {
+                period: self.period()?,
+            },
+            "for" => Hook::OnlyFor {
+                duration_secs: self.duration()?,
+            },
+            "idle" => Hook::IdleTimeout {
+                idle_secs: self.duration()?,
+            },
+            "max" => {
+                let max_uses = self.number()?;
+                self.expect("uses")?;
+                self.expect("per")?;
+                Hook::RateLimit {
+                    max_uses,
+                    window_secs: self.duration()?,
+                }
+            }
+            "at" => {
+                let cron = match self.next_tok() {
+                    Some(Tok::Str(cron)) => cron,
+                    _ => return Err(self.error_before("expected a quoted cron expression")),
+                };
+                self.expect("for")?;
+                Hook::OnlyAt {
+                    cron,
+                    window_secs: self.duration()?,
+                }
+            }
+            "blackout" => Hook::Blackout {
+                calendar: self.name("a calendar name")?,
+            },
+            "during" if self.peek_word_at(0).as_deref() == Some("none") && !self.at_window(0) => {
+                self.pos += 1;
+                Hook::OnlyDuring {
+                    windows: Vec::new(),
+                }
+            }
+            "during" => Hook::OnlyDuring {
+                windows: self.windows()?,
+            },
+            _ if parse_days(&word).is_some() => {
+                self.pos -= 1;
+                Hook::OnlyDuring {
+                    windows: self.windows()?,
+                }
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error(format!(
+                    "expected a rule such as 'before', 'within' or 'weekdays 09:00-17:00', found '{}'",
+                    word
+                )));
+            }
+        };
+        Ok(hook)
+    }
+
+    /// Parses the inside of parentheses: one rule, or rules joined by `and` or `or`.
+    fn group(&mut self) -> Result<Hook> {
+        let mut hooks = vec![self.rule()?];
+        let mut joiner = None;
+        while self.joiner(&mut joiner)? {
+            if self.setting()? {
+                return Err(self.error_before("settings are only allowed at the top level"));
+            }
+            hooks.push(self.rule()?);
+        }
+        Ok(match joiner {
+            None => hooks.remove(0),
+            Some(Joiner::And) => Hook::all_of(hooks),
+            Some(Joiner::Or) => Hook::any_of(hooks),
+        })
+    }
+
+    fn close(&mut self) -> Result<()> {
+        match self.next_tok() {
+            Some(Tok::RParen) => Ok(()),
+            _ => Err(self.error_before("expected ')'")),
+        }
+    }
+
+    /// Parses `days HH:MM-HH:MM [timezone]`, repeated with commas.
+    fn windows(&mut self) -> Result<Vec<Window>> {
+        let mut windows = Vec::new();
+        loop {
+            let mut days = Vec::new();
+            loop {
+                let word = self.word("days such as 'weekdays' or 'mon,wed'")?;
+                let parsed = parse_days(&word.to_lowercase())
+                    .ok_or_else(|| self.error_before(format!("unknown day '{}'", word)))?;
+                days.extend(parsed);
+                if self.peek() == Some(&Tok::Comma) {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+            let range = self.word("a time range such as 09:00-17:00")?;
+            let (start, end) = range
+                .split_once('-')
+                .and_then(|(s, e)| Some((parse_time(s)?, parse_time(e)?)))
+                .ok_or_else(|| self.error_before(format!("invalid time range '{}'", range)))?;
+            windows.push(Window::new(days, start, end));
+
+            if let Some(word) = self.peek_word_raw() {
+                if word.contains('/') || word == "UTC" {
+                    self.pos += 1;
+                    self.set_timezone(word)?;
+                }
+            }
+            if self.peek() == Some(&Tok::Comma) && self.at_window(1) {
+                self.pos += 1;
+            } else {
+                return Ok(windows);
+            }
+        }
+    }
+
+    /// True if the token at `offset` starts a new `days HH:MM-HH:MM` window.
+    fn at_window(&self, offset: usize) -> bool {
+        let mut i = self.pos + offset;
+        loop {
+            match self.tokens.get(i).map(|t| &t.tok) {
+                Some(Tok::Word(w)) if parse_days(&w.to_lowercase()).is_some() => i += 1,
+                _ => return false,
+            }
+            match self.tokens.get(i).map(|t| &t.tok) {
+                Some(Tok::Comma) => i += 1,
+                Some(Tok::Word(w)) => return w.contains(':') && w.contains('-'),
+                _ => return false,
+            }
+        }
+    }
+
+    fn period(&mut self) -> Result<Period> {
+        let word = self.word("a date, time, range or duration")?;
+        let invalid = || format!("invalid date, time or duration '{}'", word);
+
+        if let Some((start, end)) = word.split_once("..") {
+            let start = parse_moment(start, false).ok_or_else(|| self.error_before(invalid()))?;
+            let end = parse_moment(end, true).ok_or_else(|| self.error_before(invalid()))?;
+            return match (start, end) {
+                (Moment::Utc(start), Moment::Utc(end)) => Ok(Period::Range { start, end }),
+                (Moment::Local(start), Moment::Local(end)) => Ok(Period::LocalRange { start, end }),
+                _ => Err(self.error_before(
+                    "both ends of a range must be local times, or both must have an offset",
+                )),
+            };
+        }
+        if let Some(seconds) = parse_duration(&word) {
+            return Ok(Period::Duration { seconds });
+        }
+        match parse_moment(&word, false).ok_or_else(|| self.error_before(invalid()))? {
+            Moment::Utc(value) => Ok(Period::Instant { value }),
+            Moment::Local(value) => Ok(Period::LocalInstant { value }),
+        }
+    }
+
+    fn duration(&mut self) -> Result<u64> {
+        let word = self.word("a duration such as 30m or 1d12h")?;
+        parse_duration(&word)
+            .ok_or_else(|| self.error_before(format!("invalid duration '{}'", word)))
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T> {
+        let word = self.word("a number")?;
+        word.parse()
+            .map_err(|_| self.error_before(format!("invalid number '{}'", word)))
+    }
+
+    /// Unknown zone names are left for `Policy::validate` to report.
+    fn set_timezone(&mut self, timezone: String) -> Result<()> {
+        match &self.policy.timezone {
+            Some(existing) if *existing != timezone => Err(self.error_before(format!(
+                "timezone '{}' conflicts with '{}'",
+                timezone, existing
+            ))),
+            _ => {
+                self.policy.timezone = Some(timezone);
+                Ok(())
+            }
+        }
+    }
+
+    fn expect(&mut self, keyword: &str) -> Result<()> {
+        match self.peek_word_at(0) {
+            Some(word) if word == keyword => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error(format!("expected '{}'", keyword))),
+        }
+    }
+
+    /// A bare word or quoted string, such as an id or calendar name.
+    fn name(&mut self, what: &str) -> Result<String> {
+        match self.next_tok() {
+            Some(Tok::Word(w)) | Some(Tok::Str(w)) => Ok(w),
+            _ => Err(self.error_before(format!("expected {}", what))),
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<String> {
+        match self.next_tok() {
+            Some(Tok::Word(w)) => Ok(w),
+            _ => Err(self.error_before(format!("expected {}", what))),
+        }
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn peek_word_raw(&self) -> Option<String> {
+        match self.peek() {
+            Some(Tok::Word(w)) => Some(w.clone()),
+            _ => None,
+        }
+    }
+
+    /// The lowercased word `offset` tokens ahead, if that token is a word.
+    fn peek_word_at(&self, offset: usize) -> Option<String> {
+        match self.tokens.get(self.pos + offset).map(|t| &t.tok) {
+            Some(Tok::Word(w)) => Some(w.to_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn next_tok(&mut self) -> Option<Tok> {
+        let tok = self.tokens.get(self.pos).map(|t| t.tok.clone());
+        self.pos += 1;
+        tok
+    }
+
+    /// An error at the current token.
+    fn error(&self, message: impl Into<String>) -> Error {
+        self.error_at(self.pos, message)
+    }
+
+    /// An error at the token just consumed.
+    fn error_before(&self, message: impl Into<String>) -> Error {
+        self.error_at(self.pos.saturating_sub(1), message)
+    }
+
+    fn error_at(&self, index: usize, message: impl Into<String>) -> Error {
+        match self.tokens.get(index).or(self.tokens.last()) {
+            Some(token) => syntax_error(token.line, token.column, message),
+            None => syntax_error(1, 1, message),
+        }
+    }
+}
+
+enum Moment {
+    Utc(DateTime<Utc>),
+    Local(NaiveDateTime),
+}
+
+/// Parses an RFC 3339 instant, a local date-time, or a bare date.
+///
+/// A bare date means midnight, or 23:59:59 when `end_of_range` is set.
+fn parse_moment(value: &str, end_of_range: bool) -> Option<Moment> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(Moment::Utc(dt.with_timezone(&Utc)));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if end_of_range {
+            end_of_day()
+        } else {
+            NaiveTime::MIN
+        };
+        return Some(Moment::Local(date.and_time(time)));
+    }
+    let (date, time) = value.split_once(['T', 't'])?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(Moment::Local(date.and_time(parse_time(time)?)))
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S%.f")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .ok()
+}
+
+/// Parses durations such as `90s`, `30m` or `1d12h`.
+pub fn parse_duration(value: &str) -> Option<u64> {
+    if value.is_empty() {
+        return None;
+    }
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let size = match c {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let amount: u64 = digits.parse().ok()?;
+        total = total.checked_add(amount.checked_mul(size)?)?;
+        digits.clear();
+    }
+    digits.is_empty().then_some(total)
+}
+
+/// Parses one comma-separated day item: a day, a range such as `mon-fri`, or a shorthand.
+fn parse_days(value: &str) -> Option<Vec<Weekday>> {
+    match value {
+        "weekdays" => return Some(WEEKDAYS.to_vec()),
+        "weekends" => return Some(WEEKENDS.to_vec()),
+        "daily" => return Some(DAILY.to_vec()),
+        "none" => return Some(Vec::new()),
+        _ => {}
+    }
+    if let Some((first, last)) = value.split_once('-') {
+        let (mut day, last) = (
+            first.parse::<Weekday>().ok()?,
+            last.parse::<Weekday>().ok()?,
+        );
+        let mut days = vec![day];
+        while day != last {
+            day = day.succ();
+            days.push(day);
+        }
+        return Some(days);
+    }
+    value.parse::<Weekday>().ok().map(|day| vec![day])
+}
@@ -29,6 +29,31 @@ pub enum Error {
     #[error("Invalid period: {0}")]
     InvalidPeriod(String),
 
+    #[error("Unknown timezone: {0}")]
+    InvalidTimezone(String),
+
+    #[error("Invalid policy: {0}")]
+    InvalidPolicy(String),
+
+    #[error("Policy syntax error at line {line}, column {column}: {message}")]
+    PolicySyntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    #[error("Invalid calendar: {0}")]
+    InvalidCalendar(String),
+
+    #[error("Clock rollback detected: current time {now} is before {high_water}, the latest time this store has seen")]
+    ClockRollback {
+        now: chrono::DateTime<chrono::Utc>,
+        high_water: chrono::DateTime<chrono::Utc>,
+    },
+
+    #[error("Trusted time unavailable: {0}")]
+    UntrustedTime(String),
+
     #[error("Store error: {0}")]
     Store(String),
 }
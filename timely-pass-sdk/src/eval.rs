@@ -1,7 +1,12 @@
-use crate::policy::{Hook, Period, Policy};
-use chrono::{DateTime, Utc};
+use crate::calendar::{Calendar, CalendarEntry};
+use crate::clock::{Clock, SystemClock};
+use crate::policy::{local_to_utc, parse_cron, DisabledMode, Hook, Policy};
+use crate::store::Credential;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Verdict {
@@ -10,22 +15,191 @@ pub enum Verdict {
     Expired,
     NotYetValid,
     PolicyViolation(String),
+    Disabled,
+    RateLimited { retry_after: DateTime<Utc> },
+    Locked { until: DateTime<Utc> }, // too many denied attempts, see `Policy::lockout`
 }
 
+#[derive(Clone)]
 pub struct EvaluationContext {
     pub now: DateTime<Utc>,
     pub created_at: Option<DateTime<Utc>>, // For relative policies like OnlyFor
     pub last_used_at: Option<DateTime<Utc>>,
     pub usage_count: u64,
+    pub access_history: Vec<DateTime<Utc>>, // recent successful accesses, for RateLimit
+    pub calendars: HashMap<String, Calendar>, // calendars referenced by Blackout hooks
 }
 
 impl Default for EvaluationContext {
     fn default() -> Self {
+        Self::with_clock(&SystemClock)
+    }
+}
+
+impl EvaluationContext {
+    /// An empty context at the clock's current time.
+    pub fn with_clock(clock: &dyn Clock) -> Self {
         Self {
-            now: Utc::now(),
+            now: clock.now(),
             created_at: None,
             last_used_at: None,
             usage_count: 0,
+            access_history: Vec::new(),
+            calendars: HashMap::new(),
+        }
+    }
+
+    /// Builds a context from a credential's creation time and usage statistics.
+    pub fn for_credential(cred: &Credential, now: DateTime<Utc>) -> Self {
+        Self {
+            now,
+            created_at: Some(cred.created_at),
+            last_used_at: cred.last_used_at,
+            usage_count: cred.usage_counter,
+            access_history: cred.access_history.clone(),
+            calendars: HashMap::new(),
+        }
+    }
+}
+
+/// Outcome of an `allOf`, `anyOf` or `not` hook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchOutcome {
+    /// Dotted path of the composite hook, e.g. `"1.0"` is the first child of the second hook.
+    pub path: String,
+    pub satisfied: bool,
+    /// Path of the child that decided the outcome: the satisfied alternative of an
+    /// `anyOf`, the failing child of an `allOf`, or the matching child of a `not`.
+    pub decided_by: Option<String>,
+}
+
+/// A time boundary a hook compared `now` against.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Boundary {
+    pub label: String,
+    pub at: DateTime<Utc>,        // as configured
+    pub effective: DateTime<Utc>, // after widening by the clock skew
+}
+
+/// Outcome of a single hook, recorded by `Policy::explain`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookTrace {
+    /// Dotted path of the hook, in the same format as `BranchOutcome::path`.
+    pub path: String,
+    pub kind: String,
+    pub passed: bool,
+    pub boundaries: Vec<Boundary>,
+    pub skew_secs: u64,
+    pub observed: Option<String>, // state that is not a time boundary, e.g. uses in window
+    pub reason: Option<String>,   // why the hook failed
+}
+
+/// Why a policy denied access.
+///
+/// Boundaries are the configured instants, before widening by the clock skew;
+/// they are `None` when the hook has no usable value (for example an
+/// `onlyBefore` given a range). `Display` gives the English text that is also
+/// stored under `details["reason"]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Reason {
+    Disabled,
+    InvalidTimezone {
+        timezone: String,
+    },
+    SingleUseConsumed,
+    MaxAttemptsExceeded {
+        max_attempts: u32,
+        used: u64,
+    },
+    Expired {
+        at: Option<DateTime<Utc>>,
+    }, // onlyBefore
+    NotYetValid {
+        from: Option<DateTime<Utc>>,
+    }, // onlyAfter
+    OutsideWindow {
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    }, // onlyWithin
+    DurationElapsed {
+        at: Option<DateTime<Utc>>,
+    }, // onlyFor
+    IdleTimeout {
+        at: Option<DateTime<Utc>>,
+    },
+    RateLimited {
+        max_uses: u32,
+        window_secs: u64,
+        retry_after: DateTime<Utc>,
+    },
+    OutsideSchedule, // onlyDuring
+    OutsideCronWindow {
+        cron: String,
+    },
+    BlackoutDate {
+        calendar: String,
+        date: NaiveDate, // in the policy timezone
+        label: Option<String>,
+    },
+    UnknownCalendar {
+        calendar: String,
+    },
+    NoAlternativeSatisfied, // anyOf
+    ExcludedByNegation,     // not
+    LockedOut {
+        until: DateTime<Utc>,
+        failures: u32,
+    },
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Disabled => write!(f, "Policy disabled"),
+            Reason::InvalidTimezone { timezone } => write!(f, "Unknown timezone: {}", timezone),
+            Reason::SingleUseConsumed => write!(f, "Single use policy violation"),
+            Reason::MaxAttemptsExceeded { .. } => write!(f, "Max attempts exceeded"),
+            Reason::Expired { .. } => write!(f, "Expired (After allowed time)"),
+            Reason::NotYetValid { .. } => write!(f, "NotYetValid (Before allowed time)"),
+            Reason::OutsideWindow { .. } => write!(f, "Outside allowed window"),
+            Reason::DurationElapsed { .. } => write!(f, "Expired (Duration elapsed)"),
+            Reason::IdleTimeout { .. } => write!(f, "Expired (Idle timeout)"),
+            Reason::RateLimited { .. } => write!(f, "Rate limit exceeded"),
+            Reason::OutsideSchedule => write!(f, "Outside scheduled hours"),
+            Reason::OutsideCronWindow { .. } => write!(f, "Outside cron window"),
+            Reason::BlackoutDate { .. } => write!(f, "Blackout date"),
+            Reason::UnknownCalendar { .. } => write!(f, "Unknown calendar"),
+            Reason::NoAlternativeSatisfied => write!(f, "No alternative satisfied"),
+            Reason::ExcludedByNegation => write!(f, "Excluded by negated hook"),
+            Reason::LockedOut { .. } => write!(f, "Locked after repeated failures"),
+        }
+    }
+}
+
+/// A flag on an accepted evaluation: access ends soon, or has already ended and
+/// is only allowed by the policy's grace period.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Warning {
+    ExpiresSoon {
+        at: DateTime<Utc>,
+    },
+    InGracePeriod {
+        expired_at: DateTime<Utc>,
+        until: DateTime<Utc>, // when access actually ends
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::ExpiresSoon { at } => write!(f, "Expires at {}", at),
+            Warning::InGracePeriod { expired_at, until } => write!(
+                f,
+                "Expired at {}; allowed during the grace period until {}",
+                expired_at, until
+            ),
         }
     }
 }
@@ -33,108 +207,1108 @@ impl Default for EvaluationContext {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PolicyEvaluation {
     pub verdict: Verdict,
-    pub matched_hooks: Vec<usize>, // indices of matched hooks
+    pub matched_hooks: Vec<usize>,    // indices of matched hooks
+    pub branches: Vec<BranchOutcome>, // composite hooks, innermost first
+    #[serde(default)]
+    pub reason: Option<Reason>, // set whenever access is denied
     pub details: HashMap<String, String>,
+    #[serde(default)]
+    pub trace: Vec<HookTrace>, // every hook, parents first; only filled by `Policy::explain`
+    #[serde(default)]
+    pub warning: Option<Warning>, // only on accepted evaluations
+}
+
+/// A future change between access being granted and denied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    pub at: DateTime<Utc>,
+    pub verdict: Verdict, // verdict from `at` onwards
+}
+
+/// A failed hook, identified by its path in the hook tree.
+struct Failure {
+    path: Vec<usize>,
+    verdict: Verdict,
+    reason: Reason,
+}
+
+struct Evaluator<'a> {
+    ctx: &'a EvaluationContext,
+    tz: Tz,
+    skew: Duration,  // tolerance added to every time boundary
+    grace: Duration, // extra time allowed past boundaries that expire access
+    branches: Vec<BranchOutcome>,
+}
+
+fn format_path(path: &[usize]) -> String {
+    path.iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// A policy setting in seconds as a `Duration`, saturating at the longest one
+/// chrono can represent.
+pub(crate) fn seconds(secs: u64) -> Duration {
+    i64::try_from(secs)
+        .ok()
+        .and_then(Duration::try_seconds)
+        .unwrap_or(Duration::MAX)
+}
+
+/// `time` moved by each of `by` in turn, or `None` if that leaves the range of
+/// `DateTime`. Callers treat `None` as a boundary that is never reached.
+pub(crate) fn shifted(time: DateTime<Utc>, by: &[Duration]) -> Option<DateTime<Utc>> {
+    by.iter().try_fold(time, |t, d| t.checked_add_signed(*d))
+}
+
+fn child_path(path: &[usize], index: usize) -> Vec<usize> {
+    let mut child = path.to_vec();
+    child.push(index);
+    child
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(ctx: &'a EvaluationContext, tz: Tz, skew: Duration) -> Self {
+        Self {
+            ctx,
+            tz,
+            skew,
+            grace: Duration::zero(),
+            branches: Vec::new(),
+        }
+    }
+
+    /// Checks top-level hooks with AND semantics, stopping at the first failure.
+    fn run(&mut self, hooks: &[Hook]) -> (Vec<usize>, Option<Failure>) {
+        let mut matched_hooks = Vec::new();
+        for (i, hook) in hooks.iter().enumerate() {
+            if let Err(failure) = self.check(hook, &[i]) {
+                return (matched_hooks, Some(failure));
+            }
+            matched_hooks.push(i);
+        }
+        (matched_hooks, None)
+    }
+
+    fn check(&mut self, hook: &Hook, path: &[usize]) -> Result<(), Failure> {
+        match hook {
+            Hook::AllOf { hooks } => {
+                let result = hooks
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(i, child)| self.check(child, &child_path(path, i)));
+                let decided_by = result.as_ref().err().map(|f| format_path(&f.path));
+                self.record(path, result.is_ok(), decided_by);
+                result
+            }
+            Hook::AnyOf { hooks } => {
+                let mut failures = Vec::new();
+                for (i, alternative) in hooks.iter().enumerate() {
+                    let child = child_path(path, i);
+                    match self.check(alternative, &child) {
+                        Ok(()) => {
+                            self.record(path, true, Some(format_path(&child)));
+                            return Ok(());
+                        }
+                        Err(failure) => failures.push(failure),
+                    }
+                }
+                self.record(path, false, None);
+
+                // If every alternative failed the same way (e.g. all expired), keep that verdict.
+                let verdict = match failures.first() {
+                    Some(first)
+                        if matches!(first.verdict, Verdict::Expired | Verdict::NotYetValid)
+                            && failures.iter().all(|f| f.verdict == first.verdict) =>
+                    {
+                        first.verdict.clone()
+                    }
+                    _ => Verdict::PolicyViolation(Reason::NoAlternativeSatisfied.to_string()),
+                };
+                Err(Failure {
+                    path: path.to_vec(),
+                    verdict,
+                    reason: Reason::NoAlternativeSatisfied,
+                })
+            }
+            Hook::Not { hook } => {
+                let child = child_path(path, 0);
+                match self.check(hook, &child) {
+                    Ok(()) => {
+                        self.record(path, false, Some(format_path(&child)));
+                        Err(Failure {
+                            path: path.to_vec(),
+                            verdict: Verdict::PolicyViolation(
+                                Reason::ExcludedByNegation.to_string(),
+                            ),
+                            reason: Reason::ExcludedByNegation,
+                        })
+                    }
+                    Err(_) => {
+                        self.record(path, true, Some(format_path(&child)));
+                        Ok(())
+                    }
+                }
+            }
+            leaf => {
+                if self.leaf_passes(leaf) {
+                    Ok(())
+                } else {
+                    Err(self.leaf_failure(leaf, path))
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, path: &[usize], satisfied: bool, decided_by: Option<String>) {
+        self.branches.push(BranchOutcome {
+            path: format_path(path),
+            satisfied,
+            decided_by,
+        });
+    }
+
+    fn leaf_passes(&self, hook: &Hook) -> bool {
+        let ctx = self.ctx;
+        let tz = &self.tz;
+        let skew = self.skew;
+        match hook {
+            Hook::OnlyBefore { period } => match period.instant(tz) {
+                Some(value) => shifted(value, &[skew, self.grace]).is_none_or(|end| ctx.now < end),
+                None => false, // Invalid period type for OnlyBefore
+            },
+            Hook::OnlyAfter { period } => match period.instant(tz) {
+                Some(value) => shifted(value, &[-skew]).is_none_or(|start| ctx.now > start),
+                None => false,
+            },
+            Hook::OnlyWithin { period } => match period.range(tz) {
+                Some((start, end)) => {
+                    shifted(start, &[-skew]).is_none_or(|start| ctx.now >= start)
+                        && shifted(end, &[skew, self.grace]).is_none_or(|end| ctx.now <= end)
+                }
+                None => false,
+            },
+            Hook::OnlyFor { duration_secs } => {
+                if let Some(created) = ctx.created_at {
+                    shifted(created, &[seconds(*duration_secs), skew, self.grace])
+                        .is_none_or(|end| ctx.now <= end)
+                } else {
+                    // If we don't know creation time, we can't enforce OnlyFor, so we might fail closed?
+                    // Or maybe it's a configuration error. Fail closed for security.
+                    false
+                }
+            }
+            Hook::IdleTimeout { idle_secs } => {
+                // A credential that was never used is idle since its creation.
+                match ctx.last_used_at.or(ctx.created_at) {
+                    Some(anchor) => shifted(anchor, &[seconds(*idle_secs), skew, self.grace])
+                        .is_none_or(|end| ctx.now <= end),
+                    None => false,
+                }
+            }
+            Hook::RateLimit {
+                max_uses,
+                window_secs,
+            } => self.uses_within(*window_secs).len() < *max_uses as usize,
+            Hook::OnlyDuring { windows } => {
+                // Accept if a window is open anywhere within the skew around `now`.
+                self.around_now().iter().any(|t| {
+                    let local = t.with_timezone(tz).naive_local();
+                    windows.iter().any(|w| w.contains(local))
+                })
+            }
+            Hook::OnlyAt { cron, window_secs } => match parse_cron(cron) {
+                Ok(schedule) => {
+                    // The window is open if the latest match at or before `now`
+                    // started less than `window_secs` ago.
+                    let Some(latest) = shifted(ctx.now, &[skew]) else {
+                        return true; // a skew that large covers every window
+                    };
+                    match schedule.find_previous_occurrence(&latest.with_timezone(tz), true) {
+                        Ok(opened) => {
+                            shifted(opened.with_timezone(&Utc), &[seconds(*window_secs), skew])
+                                .is_none_or(|closes| ctx.now < closes)
+                        }
+                        Err(_) => false,
+                    }
+                }
+                Err(_) => false, // Malformed expression, fail closed
+            },
+            Hook::Blackout { calendar } => match ctx.calendars.get(calendar) {
+                // Allow access if the clock may still be outside the blackout.
+                Some(calendar) => self.around_now().iter().any(|t| {
+                    calendar
+                        .entry_on(t.with_timezone(tz).date_naive())
+                        .is_none()
+                }),
+                None => false, // Unknown calendar, fail closed
+            },
+            Hook::AllOf { .. } | Hook::AnyOf { .. } | Hook::Not { .. } => {
+                unreachable!("composite hooks are handled by check")
+            }
+        }
+    }
+
+    /// `now` and the instants `skew` either side of it, where they exist.
+    fn around_now(&self) -> Vec<DateTime<Utc>> {
+        [-self.skew, Duration::zero(), self.skew]
+            .iter()
+            .filter_map(|d| shifted(self.ctx.now, &[*d]))
+            .collect()
+    }
+
+    /// Accesses recorded within the last `window_secs`, oldest first.
+    fn uses_within(&self, window_secs: u64) -> Vec<DateTime<Utc>> {
+        let since = shifted(self.ctx.now, &[-seconds(window_secs)]);
+        let mut uses: Vec<_> = self
+            .ctx
+            .access_history
+            .iter()
+            .copied()
+            .filter(|t| since.is_none_or(|since| *t > since) && *t <= self.ctx.now)
+            .collect();
+        uses.sort();
+        uses
+    }
+
+    /// Checks `hook` and all of its children without short-circuiting, appending
+    /// one entry per hook to `out`. Returns whether `hook` passed.
+    fn trace(&self, hook: &Hook, path: &[usize], out: &mut Vec<HookTrace>) -> bool {
+        let index = out.len();
+        out.push(HookTrace {
+            path: format_path(path),
+            kind: hook.kind().to_string(),
+            passed: false,
+            boundaries: Vec::new(),
+            skew_secs: self.skew.num_seconds() as u64,
+            observed: None,
+            reason: None,
+        });
+
+        let (passed, reason) = match hook {
+            Hook::AllOf { hooks } => {
+                let results: Vec<bool> = hooks
+                    .iter()
+                    .enumerate()
+                    .map(|(i, child)| self.trace(child, &child_path(path, i), out))
+                    .collect();
+                let passed = results.iter().all(|r| *r);
+                (
+                    passed,
+                    (!passed).then(|| "A required child failed".to_string()),
+                )
+            }
+            Hook::AnyOf { hooks } => {
+                let results: Vec<bool> = hooks
+                    .iter()
+                    .enumerate()
+                    .map(|(i, child)| self.trace(child, &child_path(path, i), out))
+                    .collect();
+                let passed = results.iter().any(|r| *r);
+                (
+                    passed,
+                    (!passed).then(|| Reason::NoAlternativeSatisfied.to_string()),
+                )
+            }
+            Hook::Not { hook: child } => {
+                let passed = !self.trace(child, &child_path(path, 0), out);
+                (
+                    passed,
+                    (!passed).then(|| Reason::ExcludedByNegation.to_string()),
+                )
+            }
+            leaf => {
+                let (boundaries, observed) = self.leaf_boundaries(leaf);
+                out[index].boundaries = boundaries;
+                out[index].observed = observed;
+                let passed = self.leaf_passes(leaf);
+                (
+                    passed,
+                    (!passed).then(|| self.leaf_failure(leaf, path).reason.to_string()),
+                )
+            }
+        };
+
+        out[index].passed = passed;
+        out[index].reason = reason;
+        passed
+    }
+
+    /// The boundaries a leaf hook compares `now` against, plus any other state it looked at.
+    /// Boundaries that fall outside the range of `DateTime` are left out.
+    fn leaf_boundaries(&self, hook: &Hook) -> (Vec<Boundary>, Option<String>) {
+        let ctx = self.ctx;
+        let tz = &self.tz;
+        let skew = self.skew;
+        let boundary = |label: &str, at: Option<DateTime<Utc>>, by: Duration| {
+            let at = at?;
+            Some(Boundary {
+                label: label.to_string(),
+                at,
+                effective: shifted(at, &[by])?,
+            })
+        };
+        let wrong_period = || Some("period kind is not supported by this hook".to_string());
+
+        let (boundaries, observed) = match hook {
+            Hook::OnlyBefore { period } => match period.instant(tz) {
+                Some(value) => (vec![boundary("before", Some(value), skew)], None),
+                None => (Vec::new(), wrong_period()),
+            },
+            Hook::OnlyAfter { period } => match period.instant(tz) {
+                Some(value) => (vec![boundary("after", Some(value), -skew)], None),
+                None => (Vec::new(), wrong_period()),
+            },
+            Hook::OnlyWithin { period } => match period.range(tz) {
+                Some((start, end)) => (
+                    vec![
+                        boundary("start", Some(start), -skew),
+                        boundary("end", Some(end), skew),
+                    ],
+                    None,
+                ),
+                None => (Vec::new(), wrong_period()),
+            },
+            Hook::OnlyFor { duration_secs } => match ctx.created_at {
+                Some(created) => {
+                    let end = shifted(created, &[seconds(*duration_secs)]);
+                    (vec![boundary("expires", end, skew)], None)
+                }
+                None => (Vec::new(), Some("creation time unknown".to_string())),
+            },
+            Hook::IdleTimeout { idle_secs } => match ctx.last_used_at.or(ctx.created_at) {
+                Some(anchor) => {
+                    let end = shifted(anchor, &[seconds(*idle_secs)]);
+                    (vec![boundary("idleExpires", end, skew)], None)
+                }
+                None => (
+                    Vec::new(),
+                    Some("no use or creation time known".to_string()),
+                ),
+            },
+            Hook::RateLimit {
+                max_uses,
+                window_secs,
+            } => {
+                let start = shifted(ctx.now, &[-seconds(*window_secs)]);
+                let used = self.uses_within(*window_secs).len();
+                (
+                    vec![boundary("windowStart", start, Duration::zero())],
+                    Some(format!("{} of {} uses in window", used, max_uses)),
+                )
+            }
+            Hook::OnlyDuring { .. } => (
+                Vec::new(),
+                Some(format!(
+                    "local time {}",
+                    ctx.now.with_timezone(tz).naive_local()
+                )),
+            ),
+            Hook::OnlyAt { cron, window_secs } => {
+                let opened = parse_cron(cron).ok().and_then(|schedule| {
+                    let local = shifted(ctx.now, &[skew])?.with_timezone(tz);
+                    schedule.find_previous_occurrence(&local, true).ok()
+                });
+                match opened {
+                    Some(opened) => {
+                        let opened = opened.with_timezone(&Utc);
+                        let closes = shifted(opened, &[seconds(*window_secs)]);
+                        (
+                            vec![
+                                boundary("opened", Some(opened), -skew),
+                                boundary("closes", closes, skew),
+                            ],
+                            None,
+                        )
+                    }
+                    None => (Vec::new(), Some("no previous cron match".to_string())),
+                }
+            }
+            Hook::Blackout { calendar } => {
+                let date = ctx.now.with_timezone(tz).date_naive();
+                match ctx.calendars.get(calendar).map(|c| c.entry_on(date)) {
+                    Some(Some(entry)) => {
+                        let (start, end) = self.blackout_span(entry);
+                        (
+                            vec![
+                                boundary("blackoutStart", Some(start), skew),
+                                boundary("blackoutEnd", Some(end), -skew),
+                            ],
+                            entry.label.clone(),
+                        )
+                    }
+                    Some(None) => (Vec::new(), Some(format!("no entry on {}", date))),
+                    None => (
+                        Vec::new(),
+                        Some(format!("calendar '{}' not found", calendar)),
+                    ),
+                }
+            }
+            Hook::AllOf { .. } | Hook::AnyOf { .. } | Hook::Not { .. } => {
+                unreachable!("composite hooks are handled by trace")
+            }
+        };
+        (boundaries.into_iter().flatten().collect(), observed)
+    }
+
+    fn leaf_failure(&self, hook: &Hook, path: &[usize]) -> Failure {
+        let ctx = self.ctx;
+        let tz = &self.tz;
+        let reason = match hook {
+            Hook::OnlyBefore { period } => Reason::Expired {
+                at: period.instant(tz),
+            },
+            Hook::OnlyAfter { period } => Reason::NotYetValid {
+                from: period.instant(tz),
+            },
+            Hook::OnlyWithin { period } => {
+                let range = period.range(tz);
+                Reason::OutsideWindow {
+                    start: range.map(|(start, _)| start),
+                    end: range.map(|(_, end)| end),
+                }
+            }
+            Hook::OnlyFor { duration_secs } => Reason::DurationElapsed {
+                at: ctx
+                    .created_at
+                    .and_then(|created| shifted(created, &[seconds(*duration_secs)])),
+            },
+            Hook::IdleTimeout { idle_secs } => Reason::IdleTimeout {
+                at: ctx
+                    .last_used_at
+                    .or(ctx.created_at)
+                    .and_then(|anchor| shifted(anchor, &[seconds(*idle_secs)])),
+            },
+            Hook::RateLimit {
+                max_uses,
+                window_secs,
+            } => {
+                // Access reopens once enough of the uses in the window have aged out.
+                let uses = self.uses_within(*window_secs);
+                let freeing = uses.len().saturating_sub(*max_uses as usize);
+                let retry_after = uses
+                    .get(freeing)
+                    .map(|t| {
+                        shifted(*t, &[seconds(*window_secs)]).unwrap_or(DateTime::<Utc>::MAX_UTC)
+                    })
+                    .unwrap_or(ctx.now);
+                Reason::RateLimited {
+                    max_uses: *max_uses,
+                    window_secs: *window_secs,
+                    retry_after,
+                }
+            }
+            Hook::OnlyDuring { .. } => Reason::OutsideSchedule,
+            Hook::OnlyAt { cron, .. } => Reason::OutsideCronWindow { cron: cron.clone() },
+            Hook::Blackout { calendar } => match ctx.calendars.get(calendar) {
+                Some(entries) => {
+                    let date = ctx.now.with_timezone(tz).date_naive();
+                    Reason::BlackoutDate {
+                        calendar: calendar.clone(),
+                        date,
+                        label: entries.entry_on(date).and_then(|e| e.label.clone()),
+                    }
+                }
+                None => Reason::UnknownCalendar {
+                    calendar: calendar.clone(),
+                },
+            },
+            Hook::AllOf { .. } | Hook::AnyOf { .. } | Hook::Not { .. } => {
+                unreachable!("composite hooks are handled by check")
+            }
+        };
+
+        let verdict = match &reason {
+            Reason::Expired { .. }
+            | Reason::DurationElapsed { .. }
+            | Reason::IdleTimeout { .. } => Verdict::Expired,
+            Reason::NotYetValid { .. } => Verdict::NotYetValid,
+            Reason::RateLimited { retry_after, .. } => Verdict::RateLimited {
+                retry_after: *retry_after,
+            },
+            _ => Verdict::PolicyViolation(reason.to_string()),
+        };
+
+        Failure {
+            path: path.to_vec(),
+            verdict,
+            reason,
+        }
+    }
 }
 
 impl Policy {
+    /// Evaluates the policy at `ctx.now`. An accepted evaluation carries a
+    /// `warning` when access is within `warn_before_secs` of expiring, or is
+    /// only allowed by `grace_period_secs`.
     pub fn evaluate(&self, ctx: &EvaluationContext) -> PolicyEvaluation {
-        let mut matched_hooks = Vec::new();
+        let mut evaluation = self.evaluate_verdict(ctx);
+        if evaluation.verdict == Verdict::Accept && evaluation.warning.is_none() {
+            evaluation.warning = self.expiry_warning(ctx);
+        }
+        if let Some(warning) = &evaluation.warning {
+            evaluation
+                .details
+                .insert("warning".to_string(), warning.to_string());
+        }
+        evaluation
+    }
+
+    /// Flags an upcoming expiry within `warn_before_secs`, ignoring the grace
+    /// period so the warning comes before access is already overdue. Recurring
+    /// schedules closing for the day do not count as expiring.
+    fn expiry_warning(&self, ctx: &EvaluationContext) -> Option<Warning> {
+        let warn = seconds(self.warn_before_secs?);
+        let strict = Policy {
+            grace_period_secs: None,
+            ..self.clone()
+        };
+        let horizon = shifted(ctx.now, &[warn]).unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let at = strict.find_change(ctx, Change::Access, Some(horizon))?.at;
+
+        // Access ends at an expiry boundary, or a second after it for the
+        // inclusive ones.
+        let evaluator = Evaluator::new(ctx, self.tz().ok()?, seconds(self.clock_skew_secs));
+        let mut expiries = Vec::new();
+        self.hooks
+            .iter()
+            .for_each(|h| evaluator.expiries(h, &mut expiries));
+        expiries
+            .iter()
+            .any(|b| {
+                b.effective <= at
+                    && shifted(b.effective, &[Duration::seconds(1)]).is_none_or(|end| at <= end)
+            })
+            .then_some(Warning::ExpiresSoon { at })
+    }
+
+    /// Evaluates the verdict (and any grace period), without looking ahead
+    /// for expiry warnings.
+    fn evaluate_verdict(&self, ctx: &EvaluationContext) -> PolicyEvaluation {
+        let matched_hooks = Vec::new();
         let mut details = HashMap::new();
 
+        // A disabled policy either blocks everything or is skipped entirely.
+        if !self.enabled {
+            let (verdict, reason) = match self.when_disabled {
+                DisabledMode::Deny => {
+                    details.insert("reason".to_string(), Reason::Disabled.to_string());
+                    (Verdict::Disabled, Some(Reason::Disabled))
+                }
+                DisabledMode::Bypass => {
+                    details.insert("policy_disabled".to_string(), "bypassed".to_string());
+                    (Verdict::Accept, None)
+                }
+            };
+            return PolicyEvaluation {
+                verdict,
+                matched_hooks,
+                branches: Vec::new(),
+                reason,
+                details,
+                trace: Vec::new(),
+                warning: None,
+            };
+        }
+
+        // Resolve the timezone used for wall-clock periods. An unknown zone is a
+        // configuration error, so fail closed.
+        let tz = match self.tz() {
+            Ok(tz) => tz,
+            Err(e) => {
+                details.insert("reason".to_string(), e.to_string());
+                return PolicyEvaluation {
+                    verdict: Verdict::PolicyViolation(e.to_string()),
+                    matched_hooks,
+                    branches: Vec::new(),
+                    reason: Some(Reason::InvalidTimezone {
+                        timezone: self.timezone.clone().unwrap_or_default(),
+                    }),
+                    details,
+                    trace: Vec::new(),
+                    warning: None,
+                };
+            }
+        };
+
         // Check single use
         if self.single_use && ctx.usage_count > 0 {
+            let reason = Reason::SingleUseConsumed;
+            details.insert("reason".to_string(), reason.to_string());
             return PolicyEvaluation {
                 verdict: Verdict::Reject,
                 matched_hooks,
-                details: {
-                    details.insert(
-                        "reason".to_string(),
-                        "Single use policy violation".to_string(),
-                    );
-                    details
-                },
+                branches: Vec::new(),
+                reason: Some(reason),
+                details,
+                trace: Vec::new(),
+                warning: None,
             };
         }
 
         // Check max attempts
         if let Some(max) = self.max_attempts {
             if ctx.usage_count >= max as u64 {
+                let reason = Reason::MaxAttemptsExceeded {
+                    max_attempts: max,
+                    used: ctx.usage_count,
+                };
+                details.insert("reason".to_string(), reason.to_string());
                 return PolicyEvaluation {
                     verdict: Verdict::Reject,
                     matched_hooks,
-                    details: {
-                        details.insert("reason".to_string(), "Max attempts exceeded".to_string());
-                        details
-                    },
+                    branches: Vec::new(),
+                    reason: Some(reason),
+                    details,
+                    trace: Vec::new(),
+                    warning: None,
                 };
             }
         }
 
-        for (i, hook) in self.hooks.iter().enumerate() {
-            let passed = match hook {
-                Hook::OnlyBefore { period } => match period {
-                    Period::Instant { value } => ctx.now < *value,
-                    _ => false, // Invalid period type for OnlyBefore
-                },
-                Hook::OnlyAfter { period } => match period {
-                    Period::Instant { value } => ctx.now > *value,
-                    _ => false,
-                },
-                Hook::OnlyWithin { period } => match period {
-                    Period::Range { start, end } => ctx.now >= *start && ctx.now <= *end,
-                    _ => false,
-                },
-                Hook::OnlyFor { duration_secs } => {
-                    if let Some(created) = ctx.created_at {
-                        let end_time = created + chrono::Duration::seconds(*duration_secs as i64);
-                        ctx.now <= end_time
-                    } else {
-                        // If we don't know creation time, we can't enforce OnlyFor, so we might fail closed?
-                        // Or maybe it's a configuration error. Fail closed for security.
-                        false
-                    }
-                }
+        // Top-level hooks are combined with AND; use `anyOf`/`not` for other logic.
+        // Time boundaries are widened by the clock skew tolerance.
+        let skew = seconds(self.clock_skew_secs);
+        let mut evaluator = Evaluator::new(ctx, tz, skew);
+        let (mut matched_hooks, mut failure) = evaluator.run(&self.hooks);
+
+        // Past an expiry, the grace period allows access if nothing else denies
+        // it. The warning names the passed expiry whose grace period ends first.
+        let mut warning = None;
+        if let (Some(grace), Some(_)) = (self.grace_period_secs, &failure) {
+            let mut lenient = Evaluator::new(ctx, tz, skew);
+            lenient.grace = seconds(grace);
+            let (matched, lenient_failure) = lenient.run(&self.hooks);
+            let mut expiries = Vec::new();
+            self.hooks
+                .iter()
+                .for_each(|h| lenient.expiries(h, &mut expiries));
+            let in_grace = expiries
+                .into_iter()
+                .filter(|b| b.effective <= ctx.now)
+                .map(|b| {
+                    let until =
+                        shifted(b.effective, &[lenient.grace]).unwrap_or(DateTime::<Utc>::MAX_UTC);
+                    (b.at, until)
+                })
+                .filter(|(_, until)| *until >= ctx.now)
+                .min_by_key(|(_, until)| *until);
+            if let (None, Some((expired_at, until))) = (lenient_failure, in_grace) {
+                warning = Some(Warning::InGracePeriod { expired_at, until });
+                matched_hooks = matched;
+                failure = None;
+                evaluator = lenient;
+            }
+        }
+
+        if let Some(failure) = failure {
+            details.insert("failed_hook_index".to_string(), failure.path[0].to_string());
+            details.insert("failed_hook_path".to_string(), format_path(&failure.path));
+            details.insert("reason".to_string(), failure.reason.to_string());
+            details.insert(
+                "local_time".to_string(),
+                ctx.now.with_timezone(&tz).to_rfc3339(),
+            );
+
+            return PolicyEvaluation {
+                verdict: failure.verdict,
+                matched_hooks, // Only previously matched ones
+                branches: evaluator.branches,
+                reason: Some(failure.reason),
+                details,
+                trace: Vec::new(),
+                warning: None,
             };
+        }
 
-            if !passed {
-                // If ANY hook fails, the policy fails (AND logic).
-                // Wait, are hooks AND or OR?
-                // PRD doesn't explicitly say, but usually policies are restrictive "OnlyBefore X AND OnlyAfter Y".
-                // "accepts only if..." implies restrictive.
-                // So if any hook fails, we reject.
-
-                // However, we need to return specific verdicts.
-                let reason = match hook {
-                    Hook::OnlyBefore { .. } => "Expired (After allowed time)",
-                    Hook::OnlyAfter { .. } => "NotYetValid (Before allowed time)",
-                    Hook::OnlyWithin { .. } => "Outside allowed window",
-                    Hook::OnlyFor { .. } => "Expired (Duration elapsed)",
-                };
+        // Flag accepts that would have been denied on a perfectly synchronized clock.
+        if self.clock_skew_secs > 0
+            && Evaluator::new(ctx, tz, Duration::zero())
+                .run(&self.hooks)
+                .1
+                .is_some()
+        {
+            details.insert(
+                "clock_skew_applied_secs".to_string(),
+                self.clock_skew_secs.to_string(),
+            );
+        }
 
-                details.insert("failed_hook_index".to_string(), i.to_string());
-                details.insert("reason".to_string(), reason.to_string());
+        PolicyEvaluation {
+            verdict: Verdict::Accept,
+            matched_hooks,
+            branches: evaluator.branches,
+            reason: None,
+            details,
+            trace: Vec::new(),
+            warning,
+        }
+    }
+
+    /// Evaluates like `evaluate`, but also checks every hook instead of stopping at
+    /// the first failure and records each outcome in `trace`. `matched_hooks` then
+    /// lists every top-level hook that passed.
+    pub fn explain(&self, ctx: &EvaluationContext) -> PolicyEvaluation {
+        let mut evaluation = self.evaluate(ctx);
+        let Ok(tz) = self.tz() else {
+            return evaluation; // the verdict already reports the bad timezone
+        };
+
+        let mut evaluator = Evaluator::new(ctx, tz, seconds(self.clock_skew_secs));
+        if let Some(Warning::InGracePeriod { .. }) = evaluation.warning {
+            evaluator.grace = seconds(self.grace_period_secs.unwrap_or(0));
+        }
+        let mut trace = Vec::new();
+        evaluation.matched_hooks = self
+            .hooks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, hook)| evaluator.trace(hook, &[i], &mut trace).then_some(i))
+            .collect();
+        evaluation.trace = trace;
+        evaluation
+    }
+}
+
+/// Upper bound on the instants `next_transition` evaluates.
+const MAX_TRANSITION_STEPS: usize = 100_000;
+
+/// How far past the last fixed boundary `next_transition` follows recurring schedules.
+const TRANSITION_HORIZON_DAYS: i64 = 366;
+
+/// Returns true for leaf hooks that stop passing for good at some instant, and
+/// so can warn ahead of time and allow a grace period.
+fn expires(hook: &Hook) -> bool {
+    matches!(
+        hook,
+        Hook::OnlyBefore { .. }
+            | Hook::OnlyWithin { .. }
+            | Hook::OnlyFor { .. }
+            | Hook::IdleTimeout { .. }
+    )
+}
 
-                let verdict = match hook {
-                    Hook::OnlyBefore { .. } | Hook::OnlyFor { .. } => Verdict::Expired,
-                    Hook::OnlyAfter { .. } => Verdict::NotYetValid,
-                    _ => Verdict::PolicyViolation(reason.to_string()),
+/// Returns true if the hook contains a recurring schedule, whose boundaries
+/// cannot be listed up front.
+fn is_recurring(hook: &Hook) -> bool {
+    match hook {
+        Hook::OnlyDuring { .. } | Hook::OnlyAt { .. } => true,
+        Hook::AllOf { hooks } | Hook::AnyOf { hooks } => hooks.iter().any(is_recurring),
+        Hook::Not { hook } => is_recurring(hook),
+        _ => false,
+    }
+}
+
+impl Evaluator<'_> {
+    /// Collects the boundaries at which expiring hooks below `hook` stop passing,
+    /// such as the end of an `onlyWithin` range. Hooks under `not` are skipped,
+    /// since their expiry grants access rather than ending it.
+    fn expiries(&self, hook: &Hook, out: &mut Vec<Boundary>) {
+        match hook {
+            Hook::AllOf { hooks } | Hook::AnyOf { hooks } => {
+                hooks.iter().for_each(|h| self.expiries(h, out))
+            }
+            leaf if expires(leaf) => out.extend(
+                self.leaf_boundaries(leaf)
+                    .0
+                    .into_iter()
+                    .filter(|b| b.label != "start"),
+            ),
+            _ => {}
+        }
+    }
+
+    /// Collects the instants at which non-recurring hooks can change outcome. Each
+    /// boundary is followed by the next second, since some comparisons are exclusive.
+    fn fixed_edges(&self, hook: &Hook, out: &mut Vec<DateTime<Utc>>) {
+        let edges = match hook {
+            Hook::AllOf { hooks } | Hook::AnyOf { hooks } => {
+                hooks.iter().for_each(|h| self.fixed_edges(h, out));
+                return;
+            }
+            Hook::Not { hook } => return self.fixed_edges(hook, out),
+            Hook::RateLimit { window_secs, .. } => {
+                // A use stops counting once it leaves the window.
+                let window = seconds(*window_secs);
+                self.ctx
+                    .access_history
+                    .iter()
+                    .filter_map(|t| shifted(*t, &[window]))
+                    .collect()
+            }
+            Hook::Blackout { calendar } => match self.ctx.calendars.get(calendar) {
+                Some(calendar) => calendar
+                    .entries
+                    .iter()
+                    .flat_map(|entry| {
+                        let (start, end) = self.blackout_span(entry);
+                        [shifted(start, &[self.skew]), shifted(end, &[-self.skew])]
+                    })
+                    .flatten()
+                    .collect(),
+                None => return,
+            },
+            Hook::OnlyDuring { .. } | Hook::OnlyAt { .. } => return,
+            leaf => {
+                // Expiries also change the outcome again when the grace period ends.
+                let grace = if expires(leaf) {
+                    self.grace
+                } else {
+                    Duration::zero()
                 };
+                self.leaf_boundaries(leaf)
+                    .0
+                    .into_iter()
+                    .flat_map(|b| [Some(b.effective), shifted(b.effective, &[grace])])
+                    .flatten()
+                    .collect::<Vec<_>>()
+            }
+        };
+        for edge in edges {
+            out.push(edge);
+            out.extend(shifted(edge, &[Duration::seconds(1)]));
+        }
+    }
 
-                return PolicyEvaluation {
-                    verdict,
-                    matched_hooks, // Only previously matched ones
-                    details,
+    /// The UTC instants a calendar entry starts and ends, from local midnight to midnight.
+    fn blackout_span(&self, entry: &CalendarEntry) -> (DateTime<Utc>, DateTime<Utc>) {
+        let start = local_to_utc(&self.tz, entry.start.and_time(NaiveTime::MIN));
+        let end = local_to_utc(
+            &self.tz,
+            (entry.end + Duration::days(1)).and_time(NaiveTime::MIN),
+        );
+        (start, end)
+    }
+
+    /// The first instant after `after` at which a recurring hook below `hook` can change outcome.
+    fn next_recurring_edge(&self, hook: &Hook, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let skew = self.skew;
+        let candidates: Vec<Option<DateTime<Utc>>> = match hook {
+            Hook::AllOf { hooks } | Hook::AnyOf { hooks } => {
+                return hooks
+                    .iter()
+                    .filter_map(|h| self.next_recurring_edge(h, after))
+                    .min()
+            }
+            Hook::Not { hook } => return self.next_recurring_edge(hook, after),
+            Hook::OnlyDuring { windows } => {
+                // Weekly windows repeat, so the days around `after` hold the next edge.
+                let today = after.with_timezone(&self.tz).date_naive();
+                let mut edges = Vec::new();
+                for offset in -1..=8 {
+                    let day = today + Duration::days(offset);
+                    for w in windows.iter().filter(|w| w.days.contains(&day.weekday())) {
+                        let close_day = if w.start < w.end {
+                            day
+                        } else {
+                            day + Duration::days(1)
+                        };
+                        let open = local_to_utc(&self.tz, day.and_time(w.start));
+                        let close = local_to_utc(&self.tz, close_day.and_time(w.end));
+                        // The hook samples now and now ± skew.
+                        for edge in [open, close] {
+                            edges.extend(
+                                [-skew, Duration::zero(), skew].map(|d| shifted(edge, &[d])),
+                            );
+                        }
+                    }
+                }
+                edges
+            }
+            Hook::OnlyAt { cron, window_secs } => {
+                let schedule = parse_cron(cron).ok()?;
+                let local = shifted(after, &[skew])?.with_timezone(&self.tz);
+                let mut edges = Vec::new();
+                if let Ok(opened) = schedule.find_previous_occurrence(&local, true) {
+                    let opened = opened.with_timezone(&Utc);
+                    edges.push(shifted(opened, &[seconds(*window_secs), skew]));
+                }
+                if let Ok(next) = schedule.find_next_occurrence(&local, false) {
+                    edges.push(shifted(next.with_timezone(&Utc), &[-skew]));
+                }
+                edges
+            }
+            _ => return None,
+        };
+        candidates
+            .into_iter()
+            .flatten()
+            .flat_map(|edge| [Some(edge), shifted(edge, &[Duration::seconds(1)])])
+            .flatten()
+            .filter(|edge| *edge > after)
+            .min()
+    }
+}
+
+/// What counts as a change when searching ahead for the next transition.
+#[derive(Clone, Copy, PartialEq)]
+enum Change {
+    Access,  // switching between granted and denied
+    Verdict, // any different verdict, e.g. `NotYetValid` to `Expired`
+}
+
+/// A stretch of time over which a policy's verdict stays the same.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerdictInterval {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>, // exclusive
+    pub verdict: Verdict,
+    /// Set on the last interval when the search gave up before reaching
+    /// `until`; the timeline after `end` is unknown.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// How a search for the next change ended.
+enum Search {
+    Found(Transition),
+    Unchanged,
+    GaveUp(DateTime<Utc>), // out of steps; the verdict holds until here
+}
+
+impl Policy {
+    /// Returns the next instant after `ctx.now` at which access switches between
+    /// granted and denied, and the verdict from then on, assuming the credential
+    /// is not used in the meantime. Instants are accurate to the second.
+    ///
+    /// Returns `None` if the outcome never changes, e.g. for a disabled policy or
+    /// one that has expired for good.
+    pub fn next_transition(&self, ctx: &EvaluationContext) -> Option<Transition> {
+        self.find_change(ctx, Change::Access, None)
+    }
+
+    /// Evaluates the policy from `ctx.now` until `until` and returns the intervals
+    /// over which the verdict stays the same, in order. Like `next_transition`,
+    /// this assumes the credential is not used during the simulated period.
+    ///
+    /// If the search runs out of steps first, the intervals stop early and the
+    /// last one is marked `truncated`.
+    pub fn simulate(&self, ctx: &EvaluationContext, until: DateTime<Utc>) -> Vec<VerdictInterval> {
+        let mut intervals = Vec::new();
+        let mut probe = ctx.clone();
+        if probe.now >= until {
+            return intervals;
+        }
+        let mut verdict = self.evaluate_verdict(&probe).verdict;
+        loop {
+            let (end, next, truncated) =
+                match self.search_change(&probe, Change::Verdict, Some(until)) {
+                    Search::Found(t) if t.at < until => (t.at, Some(t), false),
+                    Search::Found(_) | Search::Unchanged => (until, None, false),
+                    Search::GaveUp(at) => (at.min(until), None, true),
                 };
+            intervals.push(VerdictInterval {
+                start: probe.now,
+                end,
+                verdict,
+                truncated,
+            });
+            match next {
+                Some(t) => {
+                    probe.now = t.at;
+                    verdict = t.verdict;
+                }
+                None => return intervals,
             }
+        }
+    }
 
-            matched_hooks.push(i);
+    /// Searches forward from `ctx.now` for the first instant the verdict changes,
+    /// giving up past `horizon` (or a year past the last fixed boundary).
+    fn find_change(
+        &self,
+        ctx: &EvaluationContext,
+        change: Change,
+        horizon: Option<DateTime<Utc>>,
+    ) -> Option<Transition> {
+        match self.search_change(ctx, change, horizon) {
+            Search::Found(transition) => Some(transition),
+            Search::Unchanged | Search::GaveUp(_) => None,
         }
+    }
 
-        PolicyEvaluation {
-            verdict: Verdict::Accept,
-            matched_hooks,
-            details,
+    fn search_change(
+        &self,
+        ctx: &EvaluationContext,
+        change: Change,
+        horizon: Option<DateTime<Utc>>,
+    ) -> Search {
+        if !self.enabled {
+            return Search::Unchanged;
+        }
+        let current = self.evaluate_verdict(ctx).verdict;
+        let Ok(tz) = self.tz() else {
+            return Search::Unchanged;
+        };
+        let skew = seconds(self.clock_skew_secs);
+        let grace = seconds(self.grace_period_secs.unwrap_or(0));
+
+        let mut probe = ctx.clone();
+        let mut fixed = Vec::new();
+        {
+            let mut evaluator = Evaluator::new(&probe, tz, skew);
+            evaluator.grace = grace;
+            self.hooks
+                .iter()
+                .for_each(|h| evaluator.fixed_edges(h, &mut fixed));
+        }
+        fixed.retain(|t| *t > ctx.now);
+        fixed.sort();
+        fixed.dedup();
+        let horizon = horizon.unwrap_or_else(|| {
+            let last = fixed.last().copied().unwrap_or(ctx.now);
+            shifted(last, &[Duration::days(TRANSITION_HORIZON_DAYS)])
+                .unwrap_or(DateTime::<Utc>::MAX_UTC)
+        });
+        let mut fixed = fixed.into_iter().peekable();
+
+        let mut cursor = ctx.now;
+        for _ in 0..MAX_TRANSITION_STEPS {
+            let mut evaluator = Evaluator::new(&probe, tz, skew);
+            evaluator.grace = grace;
+            // A denial by a hook without recurring schedules holds until the next
+            // fixed boundary, so recurring edges before it can be skipped. For the
+            // exact verdict to hold, no recurring hook may be checked before it.
+            let held = current != Verdict::Accept
+                && self
+                    .hooks
+                    .iter()
+                    .enumerate()
+                    .take_while(|(_, hook)| change == Change::Access || !is_recurring(hook))
+                    .any(|(i, hook)| !is_recurring(hook) && evaluator.check(hook, &[i]).is_err());
+            let recurring = if held {
+                None
+            } else {
+                self.hooks
+                    .iter()
+                    .filter_map(|h| evaluator.next_recurring_edge(h, cursor))
+                    .min()
+            };
+
+            while fixed.peek().is_some_and(|t| *t <= cursor) {
+                fixed.next();
+            }
+            let next = match (fixed.peek().copied(), recurring) {
+                (Some(a), Some(b)) => a.min(b),
+                (Some(next), None) | (None, Some(next)) => next,
+                (None, None) => return Search::Unchanged,
+            };
+            if next > horizon {
+                return Search::Unchanged;
+            }
+
+            probe.now = next;
+            let verdict = self.evaluate_verdict(&probe).verdict;
+            let changed = match change {
+                Change::Access => (verdict == Verdict::Accept) != (current == Verdict::Accept),
+                Change::Verdict => verdict != current,
+            };
+            if changed {
+                return Search::Found(Transition { at: next, verdict });
+            }
+            cursor = next;
         }
+        Search::GaveUp(cursor)
     }
 }
@@ -0,0 +1,251 @@
+//! Readers for store payloads written by earlier store versions.
+//!
+//! Version 1 payloads were encoded with bincode, which cannot decode the
+//! internally tagged `Hook` and `Period` enums through their derived
+//! `Deserialize` impls. The types below mirror the version 1 layout field by
+//! field and decode the tagged enums by hand.
+
+use crate::error::Result;
+use crate::policy::{Hook, Period, Policy};
+use crate::store::{AuditEntry, Credential, CredentialSecret, SecretType, StorePayload};
+use chrono::{DateTime, Utc};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Deserialize)]
+struct PayloadV1 {
+    credentials: HashMap<String, CredentialV1>,
+    policies: HashMap<String, PolicyV1>,
+    audit_logs: Vec<AuditEntryV1>,
+}
+
+#[derive(Deserialize)]
+enum SecretTypeV1 {
+    Password,
+    Key,
+    Token,
+}
+
+#[derive(Deserialize)]
+struct CredentialSecretV1 {
+    type_: SecretTypeV1,
+    data: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct CredentialV1 {
+    id: String,
+    label: String,
+    tags: Vec<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    policy_id: Option<String>,
+    secret: CredentialSecretV1,
+    usage_counter: u64,
+}
+
+#[derive(Deserialize)]
+struct PolicyV1 {
+    id: String,
+    hooks: Vec<HookV1>,
+    timezone: Option<String>,
+    clock_skew_secs: u64,
+    max_attempts: Option<u32>,
+    single_use: bool,
+    enabled: bool,
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct AuditEntryV1 {
+    timestamp: DateTime<Utc>,
+    action: String,
+    target_type: String,
+    target_id: String,
+    details: String,
+}
+
+/// A version 1 hook: the `type` tag followed by the variant's fields.
+struct HookV1(Hook);
+
+/// A version 1 period: the `type` tag followed by the variant's fields.
+struct PeriodV1(Period);
+
+fn next<'de, A, T>(
+    seq: &mut A,
+    index: usize,
+    expected: &dyn de::Expected,
+) -> std::result::Result<T, A::Error>
+where
+    A: SeqAccess<'de>,
+    T: Deserialize<'de>,
+{
+    seq.next_element()?
+        .ok_or_else(|| de::Error::invalid_length(index, expected))
+}
+
+impl<'de> Deserialize<'de> for HookV1 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct HookVisitor;
+
+        impl<'de> Visitor<'de> for HookVisitor {
+            type Value = HookV1;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a version 1 hook")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<HookV1, A::Error> {
+                let tag: String = next(&mut seq, 0, &self)?;
+                let hook = match tag.as_str() {
+                    "onlyBefore" => Hook::OnlyBefore {
+                        period: next::<_, PeriodV1>(&mut seq, 1, &self)?.0,
+                    },
+                    "onlyAfter" => Hook::OnlyAfter {
+                        period: next::<_, PeriodV1>(&mut seq, 1, &self)?.0,
+                    },
+                    "onlyWithin" => Hook::OnlyWithin {
+                        period: next::<_, PeriodV1>(&mut seq, 1, &self)?.0,
+                    },
+                    "onlyFor" => Hook::OnlyFor {
+                        duration_secs: next(&mut seq, 1, &self)?,
+                    },
+                    other => {
+                        return Err(de::Error::unknown_variant(
+                            other,
+                            &["onlyBefore", "onlyAfter", "onlyWithin", "onlyFor"],
+                        ))
+                    }
+                };
+                Ok(HookV1(hook))
+            }
+        }
+
+        // bincode only reads as many elements as the visitor asks for.
+        deserializer.deserialize_tuple(2, HookVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for PeriodV1 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct PeriodVisitor;
+
+        impl<'de> Visitor<'de> for PeriodVisitor {
+            type Value = PeriodV1;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a version 1 period")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<PeriodV1, A::Error> {
+                let tag: String = next(&mut seq, 0, &self)?;
+                let period = match tag.as_str() {
+                    "instant" => Period::Instant {
+                        value: next(&mut seq, 1, &self)?,
+                    },
+                    "range" => Period::Range {
+                        start: next(&mut seq, 1, &self)?,
+                        end: next(&mut seq, 2, &self)?,
+                    },
+                    "duration" => Period::Duration {
+                        seconds: next(&mut seq, 1, &self)?,
+                    },
+                    other => {
+                        return Err(de::Error::unknown_variant(
+                            other,
+                            &["instant", "range", "duration"],
+                        ))
+                    }
+                };
+                Ok(PeriodV1(period))
+            }
+        }
+
+        deserializer.deserialize_tuple(3, PeriodVisitor)
+    }
+}
+
+pub(crate) fn read_payload_v1(bytes: &[u8]) -> Result<StorePayload> {
+    let v1: PayloadV1 = bincode::deserialize(bytes)?;
+
+    let credentials = v1
+        .credentials
+        .into_iter()
+        .map(|(key, c)| {
+            let type_ = match c.secret.type_ {
+                SecretTypeV1::Password => SecretType::Password,
+                SecretTypeV1::Key => SecretType::Key,
+                SecretTypeV1::Token => SecretType::Token,
+            };
+            let cred = Credential {
+                id: c.id,
+                label: c.label,
+                tags: c.tags,
+                created_at: c.created_at,
+                updated_at: c.updated_at,
+                policy_ids: c.policy_id.into_iter().collect(),
+                secret: CredentialSecret {
+                    type_,
+                    data: c.secret.data,
+                },
+                usage_counter: c.usage_counter,
+                last_used_at: None, // not tracked by version 1
+                access_history: Vec::new(),
+                failed_attempts: 0,
+                last_failed_at: None,
+                locked_until: None,
+            };
+            (key, cred)
+        })
+        .collect();
+
+    let policies = v1
+        .policies
+        .into_iter()
+        .map(|(key, p)| {
+            let policy = Policy {
+                id: p.id,
+                hooks: p.hooks.into_iter().map(|h| h.0).collect(),
+                timezone: p.timezone,
+                clock_skew_secs: p.clock_skew_secs,
+                max_attempts: p.max_attempts,
+                single_use: p.single_use,
+                enabled: p.enabled,
+                version: p.version,
+                ..Default::default()
+            };
+            (key, policy)
+        })
+        .collect();
+
+    let audit_logs = v1
+        .audit_logs
+        .into_iter()
+        .map(|a| AuditEntry {
+            timestamp: a.timestamp,
+            action: a.action,
+            target_type: a.target_type,
+            target_id: a.target_id,
+            details: a.details,
+        })
+        .collect();
+
+    Ok(StorePayload {
+        credentials,
+        policies,
+        audit_logs,
+        calendars: HashMap::new(), // not supported by version 1
+        policy_history: HashMap::new(),
+        time_high_water: None,
+        tag_bindings: HashMap::new(),
+        default_policy: None,
+    })
+}
@@ -8,16 +8,25 @@
 //!
 //! ## Modules
 //!
+//! - `clock`: The `Clock` trait, with system and mock clocks.
+//! - `timesource`: Time sources that can verify the local clock (system, SNTP, signed time).
 //! - `crypto`: Cryptographic primitives (hashing, encryption, random generation).
 //! - `store`: Credential storage management.
 //! - `policy`: Policy definitions and validation.
+//! - `dsl`: The compact text syntax for writing policies.
+//! - `calendar`: Named blackout calendars referenced by policies.
 //! - `eval`: Policy evaluation logic against time.
 //! - `error`: Error types.
 
+pub mod calendar;
+pub mod clock;
 pub mod crypto;
+pub mod dsl;
 pub mod error;
 pub mod eval;
+mod legacy;
 pub mod policy;
 pub mod store;
+pub mod timesource;
 
 pub use error::Error;
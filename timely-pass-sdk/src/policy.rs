@@ -1,8 +1,19 @@
-use chrono::{DateTime, Utc};
+use crate::error::{Error, Result};
+use crate::eval::{seconds, shifted};
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use croner::Cron;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "camelCase", tag = "type")]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type", deny_unknown_fields)]
 pub enum Period {
     Instant {
         value: DateTime<Utc>,
@@ -14,45 +25,359 @@ pub enum Period {
     Duration {
         seconds: u64,
     },
+    // Wall-clock variants, interpreted in the policy's timezone.
+    LocalInstant {
+        value: NaiveDateTime,
+    },
+    LocalRange {
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "camelCase", tag = "type")]
+impl Period {
+    /// Returns the instant this period denotes, resolving wall-clock values in `tz`.
+    pub fn instant(&self, tz: &Tz) -> Option<DateTime<Utc>> {
+        match self {
+            Period::Instant { value } => Some(*value),
+            Period::LocalInstant { value } => Some(local_to_utc(tz, *value)),
+            _ => None,
+        }
+    }
+
+    /// Returns the `(start, end)` bounds of this period, resolving wall-clock values in `tz`.
+    pub fn range(&self, tz: &Tz) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        match self {
+            Period::Range { start, end } => Some((*start, *end)),
+            Period::LocalRange { start, end } => {
+                Some((local_to_utc(tz, *start), local_to_utc(tz, *end)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type", deny_unknown_fields)]
 pub enum Hook {
     OnlyBefore { period: Period },
     OnlyAfter { period: Period },
     OnlyWithin { period: Period },
     OnlyFor { duration_secs: u64 }, // interpreted as duration anchored to creation/activation
+    IdleTimeout { idle_secs: u64 }, // expires once unused for idle_secs (since last use, or creation)
+    RateLimit { max_uses: u32, window_secs: u64 }, // at most max_uses accesses per sliding window
+    OnlyDuring { windows: Vec<Window> }, // recurring weekly windows in the policy timezone
+    OnlyAt { cron: String, window_secs: u64 }, // window opened at each cron match
+    Blackout { calendar: String },  // denied on days listed in a stored calendar
+    AllOf { hooks: Vec<Hook> },     // every child must pass
+    AnyOf { hooks: Vec<Hook> },     // at least one child must pass
+    Not { hook: Box<Hook> },        // the child must fail
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+impl Hook {
+    /// Checks hook parameters that cannot be expressed in the type, such as cron syntax.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Hook::OnlyAt { cron, window_secs } => {
+                parse_cron(cron)?;
+                if *window_secs == 0 {
+                    return Err(Error::InvalidPeriod(
+                        "cron window must be at least one second".to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            Hook::AllOf { hooks } | Hook::AnyOf { hooks } => {
+                hooks.iter().try_for_each(Hook::validate)
+            }
+            Hook::Not { hook } => hook.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true if the hook reads wall-clock time, so its meaning depends on
+    /// the policy timezone.
+    pub fn uses_timezone(&self) -> bool {
+        match self {
+            Hook::OnlyBefore { period }
+            | Hook::OnlyAfter { period }
+            | Hook::OnlyWithin { period } => {
+                matches!(
+                    period,
+                    Period::LocalInstant { .. } | Period::LocalRange { .. }
+                )
+            }
+            Hook::OnlyDuring { .. } | Hook::OnlyAt { .. } | Hook::Blackout { .. } => true,
+            Hook::AllOf { hooks } | Hook::AnyOf { hooks } => hooks.iter().any(Hook::uses_timezone),
+            Hook::Not { hook } => hook.uses_timezone(),
+            Hook::OnlyFor { .. } | Hook::IdleTimeout { .. } | Hook::RateLimit { .. } => false,
+        }
+    }
+
+    /// The hook's `type` tag as written in policy files, e.g. `"onlyBefore"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Hook::OnlyBefore { .. } => "onlyBefore",
+            Hook::OnlyAfter { .. } => "onlyAfter",
+            Hook::OnlyWithin { .. } => "onlyWithin",
+            Hook::OnlyFor { .. } => "onlyFor",
+            Hook::IdleTimeout { .. } => "idleTimeout",
+            Hook::RateLimit { .. } => "rateLimit",
+            Hook::OnlyDuring { .. } => "onlyDuring",
+            Hook::OnlyAt { .. } => "onlyAt",
+            Hook::Blackout { .. } => "blackout",
+            Hook::AllOf { .. } => "allOf",
+            Hook::AnyOf { .. } => "anyOf",
+            Hook::Not { .. } => "not",
+        }
+    }
+
+    pub fn all_of(hooks: impl Into<Vec<Hook>>) -> Self {
+        Hook::AllOf {
+            hooks: hooks.into(),
+        }
+    }
+
+    pub fn any_of(hooks: impl Into<Vec<Hook>>) -> Self {
+        Hook::AnyOf {
+            hooks: hooks.into(),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(hook: Hook) -> Self {
+        Hook::Not {
+            hook: Box::new(hook),
+        }
+    }
+}
+
+/// A recurring weekly time-of-day window, e.g. Mon-Fri 09:00-17:00.
+///
+/// A window belongs to the day it starts on. If `end` is not after `start` the
+/// window crosses midnight into the following day; `start == end` spans 24 hours.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Window {
+    #[schemars(schema_with = "weekdays_schema")]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Window {
+    pub fn new(days: impl Into<Vec<Weekday>>, start: NaiveTime, end: NaiveTime) -> Self {
+        Self {
+            days: days.into(),
+            start,
+            end,
+        }
+    }
+
+    /// Returns true if the wall-clock time `local` falls inside this window.
+    pub fn contains(&self, local: NaiveDateTime) -> bool {
+        let day = local.weekday();
+        let time = local.time();
+        if self.start < self.end {
+            self.days.contains(&day) && time >= self.start && time < self.end
+        } else {
+            (self.days.contains(&day) && time >= self.start)
+                || (self.days.contains(&day.pred()) && time < self.end)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Policy {
     pub id: String,
     pub hooks: Vec<Hook>,
+    #[serde(default)]
     pub timezone: Option<String>, // e.g., "UTC" or IANA TZ
     pub clock_skew_secs: u64,
     pub max_attempts: Option<u32>,
     pub single_use: bool,
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+    #[serde(default)]
+    pub when_disabled: DisabledMode,
     pub version: u32,
+    #[serde(default)]
+    pub extends: Option<Extends>, // inherit hooks and limits from another stored policy
+    #[serde(default)]
+    pub lockout: Option<Lockout>, // lock credentials after repeated denied attempts
+    #[serde(default)]
+    pub require_trusted_time: bool, // fail closed unless a time source verifies the clock
+    #[serde(default)]
+    pub warn_before_secs: Option<u64>, // flag accesses this close to expiry
+    #[serde(default)]
+    pub grace_period_secs: Option<u64>, // allow, flagged, this long past expiry
+}
+
+/// Locks a credential once it has been denied `max_failures` times in a row.
+///
+/// Denials while locked are not counted. A successful access or an explicit
+/// unlock resets the count.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Lockout {
+    pub max_failures: u32,
+    pub cooldown_secs: u64, // length of the first lock
+    #[serde(default)]
+    pub backoff: Backoff,
+}
+
+/// How the lock grows with each further failure after the first lock.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "type", deny_unknown_fields)]
+pub enum Backoff {
+    /// Every lock lasts `cooldown_secs`.
+    #[default]
+    Fixed,
+    /// Each lock doubles the previous one, up to `max_secs`.
+    Exponential { max_secs: u64 },
+}
+
+impl Lockout {
+    /// How long to lock a credential that has now failed `failures` times in a
+    /// row, or `None` if that is not enough to lock it.
+    pub fn lock_duration(&self, failures: u32) -> Option<Duration> {
+        let extra = failures.checked_sub(self.max_failures.max(1))?;
+        let secs = match self.backoff {
+            Backoff::Fixed => self.cooldown_secs,
+            Backoff::Exponential { max_secs } => 2u64
+                .checked_pow(extra)
+                .and_then(|factor| self.cooldown_secs.checked_mul(factor))
+                .map_or(max_secs, |secs| secs.min(max_secs)),
+        };
+        Some(seconds(secs))
+    }
+}
+
+/// A reference to the base policy a policy inherits from.
+///
+/// The store resolves the chain when the policy is evaluated, so changes to a
+/// base policy apply to every policy that extends it. `clock_skew_secs` and
+/// `max_attempts` come from the base unless overridden here.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Extends {
+    pub policy: String, // id of the base policy
+    #[serde(default)]
+    pub hooks: HookMerge,
+    #[serde(default)]
+    pub clock_skew_secs: Option<u64>,
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+}
+
+/// How a derived policy's hooks combine with those of its base.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HookMerge {
+    /// Check the base hooks first, then the policy's own.
+    #[default]
+    Append,
+    /// Use only the policy's own hooks.
+    Replace,
+}
+
+/// What a disabled policy does to the credentials it guards.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DisabledMode {
+    /// Deny all access, acting as a kill switch for the credential.
+    #[default]
+    Deny,
+    /// Skip the policy entirely and allow access.
+    Bypass,
 }
 
 fn default_enabled() -> bool {
     true
 }
 
+/// Schema for `Window::days`, which chrono's `Weekday` does not describe itself.
+fn weekdays_schema(_gen: &mut SchemaGenerator) -> Schema {
+    let day = SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        enum_values: Some(
+            ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        ),
+        ..Default::default()
+    };
+    SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(schemars::schema::ArrayValidation {
+            items: Some(Schema::Object(day).into()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Turns a deserialization error into one that names the offending key, e.g.
+/// `hooks[0].period: unknown field `vaule``.
+fn locate<E: std::fmt::Display>(err: serde_path_to_error::Error<E>) -> Error {
+    let path = err.path().to_string();
+    if path == "." {
+        Error::InvalidPolicy(err.inner().to_string())
+    } else {
+        Error::InvalidPolicy(format!("{}: {}", path, err.inner()))
+    }
+}
+
+/// Resolves an IANA timezone name such as `"Europe/Berlin"`.
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.parse::<Tz>()
+        .map_err(|_| Error::InvalidTimezone(name.to_string()))
+}
+
+/// Parses a cron expression (5 fields, or 6 with leading seconds), e.g. `"0 2 * * SUN"`.
+pub fn parse_cron(expression: &str) -> Result<Cron> {
+    Cron::from_str(expression).map_err(|e| {
+        Error::InvalidPeriod(format!("invalid cron expression '{}': {}", expression, e))
+    })
+}
+
+/// Converts a wall-clock time in `tz` to UTC.
+///
+/// Times that fall into a DST gap are shifted forward by the length of the gap,
+/// and times repeated by a DST fold resolve to the earlier of the two instants.
+pub fn local_to_utc(tz: &Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => dt.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => {
+            // Apply the offset in effect before the transition.
+            let before = tz.offset_from_utc_datetime(&(local - Duration::days(1)));
+            let offset = before.fix().local_minus_utc();
+            Utc.from_utc_datetime(&(local - Duration::seconds(offset as i64)))
+        }
+    }
+}
+
 impl Default for Policy {
     fn default() -> Self {
         Self {
             id: uuid::Uuid::new_v4().to_string(),
             hooks: Vec::new(),
-            timezone: Some("UTC".to_string()),
+            timezone: None, // UTC, or the base policy's zone when extending
             clock_skew_secs: 60,
             max_attempts: None,
             single_use: false,
             enabled: true,
+            when_disabled: DisabledMode::Deny,
             version: 1,
+            extends: None,
+            lockout: None,
+            require_trusted_time: false,
+            warn_before_secs: None,
+            grace_period_secs: None,
         }
     }
 }
@@ -69,4 +394,608 @@ impl Policy {
         self.hooks.push(hook);
         self
     }
+
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Reads a policy file in JSON. Unknown keys are rejected, and errors name
+    /// the key they occurred at.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut de = serde_json::Deserializer::from_str(json);
+        let policy = serde_path_to_error::deserialize(&mut de).map_err(locate)?;
+        de.end()?;
+        Ok(policy)
+    }
+
+    /// Reads a policy file in TOML, with the same checks as `from_json`.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        serde_path_to_error::deserialize(toml::Deserializer::new(toml)).map_err(locate)
+    }
+
+    /// The JSON Schema that policy files (JSON or TOML) must follow.
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Policy)).expect("schema serializes")
+    }
+
+    /// Returns the longest `RateLimit` window in the hook tree, if any.
+    ///
+    /// Access history older than this is irrelevant to evaluation and can be dropped.
+    pub fn rate_limit_window_secs(&self) -> Option<u64> {
+        fn walk(hooks: &[Hook]) -> Option<u64> {
+            hooks
+                .iter()
+                .filter_map(|hook| match hook {
+                    Hook::RateLimit { window_secs, .. } => Some(*window_secs),
+                    Hook::AllOf { hooks } | Hook::AnyOf { hooks } => walk(hooks),
+                    Hook::Not { hook } => walk(std::slice::from_ref(hook)),
+                    _ => None,
+                })
+                .max()
+        }
+        walk(&self.hooks)
+    }
+
+    /// Applies this policy's `extends` overrides on top of an already resolved
+    /// `base`. The result no longer extends anything.
+    ///
+    /// Appended hooks that read wall-clock time keep their meaning only in the
+    /// base's timezone, so a policy that sets a different one is refused.
+    pub fn inherit_from(&self, base: &Policy) -> Result<Policy> {
+        let Some(extends) = &self.extends else {
+            return Ok(self.clone());
+        };
+        let hooks = match extends.hooks {
+            HookMerge::Append => {
+                if self.timezone.is_some()
+                    && base.hooks.iter().any(Hook::uses_timezone)
+                    && self.tz()? != base.tz()?
+                {
+                    return Err(Error::InvalidPolicy(format!(
+                        "policy '{}' uses timezone '{}', but the wall-clock hooks it appends from '{}' are in '{}'",
+                        self.id,
+                        self.tz()?,
+                        base.id,
+                        base.tz()?
+                    )));
+                }
+                base.hooks.iter().chain(&self.hooks).cloned().collect()
+            }
+            HookMerge::Replace => self.hooks.clone(),
+        };
+        Ok(Policy {
+            hooks,
+            timezone: self.timezone.clone().or_else(|| base.timezone.clone()),
+            clock_skew_secs: extends.clock_skew_secs.unwrap_or(base.clock_skew_secs),
+            max_attempts: extends.max_attempts.or(base.max_attempts),
+            lockout: self.lockout.clone().or_else(|| base.lockout.clone()),
+            single_use: self.single_use || base.single_use,
+            enabled: self.enabled && base.enabled,
+            when_disabled: if self.enabled && !base.enabled {
+                base.when_disabled
+            } else {
+                self.when_disabled
+            },
+            require_trusted_time: self.require_trusted_time || base.require_trusted_time,
+            warn_before_secs: self.warn_before_secs.or(base.warn_before_secs),
+            grace_period_secs: self.grace_period_secs.or(base.grace_period_secs),
+            extends: None,
+            ..self.clone()
+        })
+    }
+
+    /// Names of the calendars referenced by `blackout` hooks, in hook order.
+    pub fn calendar_names(&self) -> Vec<&str> {
+        fn walk<'a>(hooks: &'a [Hook], names: &mut Vec<&'a str>) {
+            for hook in hooks {
+                match hook {
+                    Hook::Blackout { calendar } if !names.contains(&calendar.as_str()) => {
+                        names.push(calendar)
+                    }
+                    Hook::AllOf { hooks } | Hook::AnyOf { hooks } => walk(hooks, names),
+                    Hook::Not { hook } => walk(std::slice::from_ref(hook), names),
+                    _ => {}
+                }
+            }
+        }
+        let mut names = Vec::new();
+        walk(&self.hooks, &mut names);
+        names
+    }
+
+    /// Resolves the policy timezone, defaulting to UTC when none is set.
+    pub fn tz(&self) -> Result<Tz> {
+        match &self.timezone {
+            Some(name) => parse_timezone(name),
+            None => Ok(Tz::UTC),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// The policy cannot work as written and is refused.
+    Error,
+    /// The policy works but is probably not what was intended.
+    Warning,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticKind {
+    UnknownTimezone,
+    InvalidPeriodKind,
+    EmptyRange,
+    InvalidCron,
+    InvalidWindow,
+    Unsatisfiable,
+    NoEffect,
+    InvalidLockout,
+    OutOfRange,
+}
+
+/// The longest duration, in seconds, a policy setting may hold: 100 years.
+/// Longer ones are refused, since adding them to a date can overflow.
+pub const MAX_DURATION_SECS: u64 = 100 * 366 * 86_400;
+
+/// A problem found by [`Policy::validate`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    /// Location in the policy, e.g. `hooks[1].hooks[0].period`.
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.path, self.message)
+    }
+}
+
+/// A time bound and the hook that set it.
+struct Bound {
+    value: DateTime<Utc>,
+    effective: DateTime<Utc>, // after widening by the clock skew
+    inclusive: bool,
+    path: String,
+}
+
+/// Lower and upper time bounds implied by a group of AND-ed hooks.
+#[derive(Default)]
+struct Bounds {
+    lower: Option<Bound>,
+    upper: Option<Bound>,
+}
+
+impl Bounds {
+    /// Requires access after `value`, or from it if `inclusive`. Like the
+    /// evaluator, the bound is moved earlier by `skew`.
+    fn raise(&mut self, value: DateTime<Utc>, skew: Duration, inclusive: bool, path: &str) {
+        let Some(effective) = shifted(value, &[-skew]) else {
+            return; // earlier than any instant, so no bound at all
+        };
+        if self.lower.as_ref().is_none_or(|b| effective > b.effective) {
+            self.lower = Some(Bound {
+                value,
+                effective,
+                inclusive,
+                path: path.to_string(),
+            });
+        }
+    }
+
+    /// Requires access before `value`, or up to it if `inclusive`. The bound
+    /// is moved later by `skew`.
+    fn cap(&mut self, value: DateTime<Utc>, skew: Duration, inclusive: bool, path: &str) {
+        let Some(effective) = shifted(value, &[skew]) else {
+            return;
+        };
+        if self.upper.as_ref().is_none_or(|b| effective < b.effective) {
+            self.upper = Some(Bound {
+                value,
+                effective,
+                inclusive,
+                path: path.to_string(),
+            });
+        }
+    }
+
+    /// Returns the conflicting bounds if no instant satisfies both.
+    fn conflict(&self) -> Option<(&Bound, &Bound)> {
+        let (lower, upper) = (self.lower.as_ref()?, self.upper.as_ref()?);
+        let empty = lower.effective > upper.effective
+            || (lower.effective == upper.effective && !(lower.inclusive && upper.inclusive));
+        (empty && lower.path != upper.path).then_some((lower, upper))
+    }
+}
+
+/// Whether any hook outside a `not` stops passing for good at some instant.
+fn has_expiry(hooks: &[Hook]) -> bool {
+    hooks.iter().any(|hook| match hook {
+        Hook::OnlyBefore { .. }
+        | Hook::OnlyWithin { .. }
+        | Hook::OnlyFor { .. }
+        | Hook::IdleTimeout { .. } => true,
+        Hook::AllOf { hooks } | Hook::AnyOf { hooks } => has_expiry(hooks),
+        _ => false,
+    })
+}
+
+struct Validator {
+    tz: Tz,
+    skew: Duration,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator {
+    fn push(&mut self, severity: Severity, kind: DiagnosticKind, path: &str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            kind,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    /// Validates hooks that must all pass, including whether they can pass together.
+    fn conjunction(&mut self, hooks: &[Hook], path: &str) {
+        let mut bounds = Bounds::default();
+        for (i, hook) in hooks.iter().enumerate() {
+            let hook_path = format!("{}[{}]", path, i);
+            self.hook(hook, &hook_path);
+            match hook {
+                Hook::OnlyAfter { period } => {
+                    if let Some(value) = period.instant(&self.tz) {
+                        bounds.raise(value, self.skew, false, &hook_path);
+                    }
+                }
+                Hook::OnlyBefore { period } => {
+                    if let Some(value) = period.instant(&self.tz) {
+                        bounds.cap(value, self.skew, false, &hook_path);
+                    }
+                }
+                Hook::OnlyWithin { period } => {
+                    if let Some((start, end)) = period.range(&self.tz) {
+                        bounds.raise(start, self.skew, true, &hook_path);
+                        bounds.cap(end, self.skew, true, &hook_path);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((lower, upper)) = bounds.conflict() {
+            let message = format!(
+                "can never be satisfied together with {}: access must be after {} but before {}",
+                lower.path,
+                lower.value.to_rfc3339(),
+                upper.value.to_rfc3339()
+            );
+            let path = upper.path.clone();
+            self.push(
+                Severity::Error,
+                DiagnosticKind::Unsatisfiable,
+                &path,
+                message,
+            );
+        }
+    }
+
+    fn duration(&mut self, secs: u64, path: &str) {
+        if secs > MAX_DURATION_SECS {
+            self.push(
+                Severity::Error,
+                DiagnosticKind::OutOfRange,
+                path,
+                format!(
+                    "{}s is longer than the maximum of {}s (100 years)",
+                    secs, MAX_DURATION_SECS
+                ),
+            );
+        }
+    }
+
+    fn instant_period(&mut self, period: &Period, path: &str, hook: &str) {
+        if period.instant(&self.tz).is_none() {
+            self.push(
+                Severity::Error,
+                DiagnosticKind::InvalidPeriodKind,
+                &format!("{}.period", path),
+                format!(
+                    "{} requires an instant or localInstant period, so it would never pass",
+                    hook
+                ),
+            );
+        }
+    }
+
+    fn hook(&mut self, hook: &Hook, path: &str) {
+        match hook {
+            Hook::OnlyFor { duration_secs } => {
+                self.duration(*duration_secs, &format!("{}.duration_secs", path))
+            }
+            Hook::IdleTimeout { idle_secs } => {
+                self.duration(*idle_secs, &format!("{}.idle_secs", path))
+            }
+            Hook::RateLimit { window_secs, .. } | Hook::OnlyAt { window_secs, .. } => {
+                self.duration(*window_secs, &format!("{}.window_secs", path))
+            }
+            _ => {}
+        }
+        match hook {
+            Hook::OnlyBefore { period } => self.instant_period(period, path, "onlyBefore"),
+            Hook::OnlyAfter { period } => self.instant_period(period, path, "onlyAfter"),
+            Hook::OnlyWithin { period } => match period.range(&self.tz) {
+                Some((start, end)) if start > end => self.push(
+                    Severity::Error,
+                    DiagnosticKind::EmptyRange,
+                    &format!("{}.period", path),
+                    format!(
+                        "range starts at {} after it ends at {}",
+                        start.to_rfc3339(),
+                        end.to_rfc3339()
+                    ),
+                ),
+                Some(_) => {}
+                None => self.push(
+                    Severity::Error,
+                    DiagnosticKind::InvalidPeriodKind,
+                    &format!("{}.period", path),
+                    "onlyWithin requires a range or localRange period, so it would never pass"
+                        .to_string(),
+                ),
+            },
+            Hook::OnlyFor { duration_secs: 0 } => self.push(
+                Severity::Warning,
+                DiagnosticKind::Unsatisfiable,
+                path,
+                "a zero duration expires as soon as the credential is created".to_string(),
+            ),
+            Hook::IdleTimeout { idle_secs: 0 } => self.push(
+                Severity::Warning,
+                DiagnosticKind::Unsatisfiable,
+                path,
+                "a zero idle timeout expires immediately after every use".to_string(),
+            ),
+            Hook::RateLimit { max_uses: 0, .. } => self.push(
+                Severity::Error,
+                DiagnosticKind::Unsatisfiable,
+                path,
+                "a rate limit of zero uses never allows access".to_string(),
+            ),
+            Hook::RateLimit { window_secs: 0, .. } => self.push(
+                Severity::Warning,
+                DiagnosticKind::NoEffect,
+                path,
+                "a zero-second rate limit window never limits anything".to_string(),
+            ),
+            Hook::OnlyDuring { windows } => {
+                if windows.is_empty() {
+                    self.push(
+                        Severity::Error,
+                        DiagnosticKind::InvalidWindow,
+                        &format!("{}.windows", path),
+                        "a schedule without windows never allows access".to_string(),
+                    );
+                }
+                for (i, window) in windows.iter().enumerate() {
+                    if window.days.is_empty() {
+                        self.push(
+                            Severity::Error,
+                            DiagnosticKind::InvalidWindow,
+                            &format!("{}.windows[{}].days", path, i),
+                            "window has no weekdays, so it never opens".to_string(),
+                        );
+                    }
+                }
+            }
+            Hook::OnlyAt { cron, window_secs } => {
+                if let Err(e) = parse_cron(cron) {
+                    self.push(
+                        Severity::Error,
+                        DiagnosticKind::InvalidCron,
+                        &format!("{}.cron", path),
+                        e.to_string(),
+                    );
+                }
+                if *window_secs == 0 {
+                    self.push(
+                        Severity::Error,
+                        DiagnosticKind::InvalidWindow,
+                        &format!("{}.window_secs", path),
+                        "cron window must be at least one second".to_string(),
+                    );
+                }
+            }
+            Hook::AllOf { hooks } => self.conjunction(hooks, &format!("{}.hooks", path)),
+            Hook::AnyOf { hooks } => {
+                if hooks.is_empty() {
+                    self.push(
+                        Severity::Error,
+                        DiagnosticKind::Unsatisfiable,
+                        path,
+                        "anyOf without alternatives never passes".to_string(),
+                    );
+                }
+                for (i, child) in hooks.iter().enumerate() {
+                    self.hook(child, &format!("{}.hooks[{}]", path, i));
+                }
+            }
+            Hook::Not { hook } => self.hook(hook, &format!("{}.hook", path)),
+            _ => {}
+        }
+    }
+}
+
+impl Policy {
+    /// Checks the policy for mistakes that would otherwise only show up as denials
+    /// at evaluation time, such as mismatched period kinds or contradictory hooks.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut validator = Validator {
+            tz: Tz::UTC,
+            skew: seconds(self.clock_skew_secs),
+            diagnostics: Vec::new(),
+        };
+
+        match self.tz() {
+            Ok(tz) => validator.tz = tz,
+            Err(e) => validator.push(
+                Severity::Error,
+                DiagnosticKind::UnknownTimezone,
+                "timezone",
+                e.to_string(),
+            ),
+        }
+
+        validator.duration(self.clock_skew_secs, "clock_skew_secs");
+        if let Some(skew) = self.extends.as_ref().and_then(|e| e.clock_skew_secs) {
+            validator.duration(skew, "extends.clock_skew_secs");
+        }
+
+        for (field, secs) in [
+            ("warn_before_secs", self.warn_before_secs),
+            ("grace_period_secs", self.grace_period_secs),
+        ] {
+            let Some(secs) = secs else { continue };
+            validator.duration(secs, field);
+            // A derived policy may get its expiring hooks from the base.
+            if self.extends.is_none() && !has_expiry(&self.hooks) {
+                validator.push(
+                    Severity::Warning,
+                    DiagnosticKind::NoEffect,
+                    field,
+                    "no effect without an onlyBefore, onlyWithin, onlyFor or idleTimeout hook"
+                        .to_string(),
+                );
+            }
+        }
+
+        if self.max_attempts == Some(0) {
+            validator.push(
+                Severity::Error,
+                DiagnosticKind::Unsatisfiable,
+                "max_attempts",
+                "max_attempts of 0 never allows access".to_string(),
+            );
+        }
+
+        if let Some(lockout) = &self.lockout {
+            if lockout.max_failures == 0 || lockout.cooldown_secs == 0 {
+                validator.push(
+                    Severity::Error,
+                    DiagnosticKind::InvalidLockout,
+                    "lockout",
+                    "max_failures and cooldown_secs must be at least 1".to_string(),
+                );
+            }
+            validator.duration(lockout.cooldown_secs, "lockout.cooldown_secs");
+            if let Backoff::Exponential { max_secs } = lockout.backoff {
+                validator.duration(max_secs, "lockout.backoff.max_secs");
+                if max_secs < lockout.cooldown_secs {
+                    validator.push(
+                        Severity::Warning,
+                        DiagnosticKind::InvalidLockout,
+                        "lockout.backoff.max_secs",
+                        format!(
+                            "shorter than cooldown_secs ({}), so every lock lasts max_secs",
+                            lockout.cooldown_secs
+                        ),
+                    );
+                }
+            }
+        }
+
+        // These come from the base policy; overrides belong in `extends`.
+        if let Some(extends) = &self.extends {
+            let defaults = Policy::default();
+            if self.clock_skew_secs != defaults.clock_skew_secs {
+                validator.push(
+                    Severity::Warning,
+                    DiagnosticKind::NoEffect,
+                    "clock_skew_secs",
+                    format!(
+                        "inherited from '{}'; set extends.clock_skew_secs to override",
+                        extends.policy
+                    ),
+                );
+            }
+            if self.max_attempts.is_some() {
+                validator.push(
+                    Severity::Warning,
+                    DiagnosticKind::NoEffect,
+                    "max_attempts",
+                    format!(
+                        "inherited from '{}'; set extends.max_attempts to override",
+                        extends.policy
+                    ),
+                );
+            }
+        }
+
+        validator.conjunction(&self.hooks, "hooks");
+        validator.diagnostics
+    }
+}
+
+/// A field that differs between two policies, as reported by [`Policy::diff`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FieldChange {
+    /// Location in the policy, e.g. `hooks[1].period.value`.
+    pub path: String,
+    pub before: Option<serde_json::Value>, // None if the field was added
+    pub after: Option<serde_json::Value>,  // None if the field was removed
+}
+
+fn diff_values(
+    path: &str,
+    before: Option<&serde_json::Value>,
+    after: Option<&serde_json::Value>,
+    out: &mut Vec<FieldChange>,
+) {
+    use serde_json::Value;
+    match (before, after) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_values(&child, a.get(key), b.get(key), out);
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => {
+            for i in 0..a.len().max(b.len()) {
+                diff_values(&format!("{}[{}]", path, i), a.get(i), b.get(i), out);
+            }
+        }
+        (a, b) if a != b => out.push(FieldChange {
+            path: path.to_string(),
+            before: a.cloned(),
+            after: b.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+impl Policy {
+    /// Lists the fields that differ between this policy and `other`, in path order.
+    pub fn diff(&self, other: &Policy) -> Vec<FieldChange> {
+        let before = serde_json::to_value(self).expect("policies serialize to JSON");
+        let after = serde_json::to_value(other).expect("policies serialize to JSON");
+        let mut changes = Vec::new();
+        diff_values("", Some(&before), Some(&after), &mut changes);
+        changes
+    }
 }
@@ -1,12 +1,20 @@
+use crate::calendar::Calendar;
+use crate::clock::{Clock, SystemClock};
 use crate::crypto::{MasterKey, Secret};
 use crate::error::{Error, Result};
-use crate::policy::Policy;
+use crate::eval::{
+    seconds, shifted, EvaluationContext, PolicyEvaluation, Reason, Transition, Verdict, Warning,
+};
+use crate::legacy;
+use crate::policy::{Policy, Severity};
+use crate::timesource::TimeSource;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use uuid::Uuid;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -31,30 +39,104 @@ pub struct Credential {
     pub tags: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    pub policy_id: Option<String>,
+    #[serde(default, alias = "policy_id", deserialize_with = "one_or_many")]
+    pub policy_ids: Vec<String>, // every one must accept, along with policies bound to `tags`
     pub secret: CredentialSecret,
     pub usage_counter: u64,
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub access_history: Vec<DateTime<Utc>>, // only kept as long as a RateLimit hook needs it
+    #[serde(default)]
+    pub failed_attempts: u32, // denied attempts since the last success or unlock
+    #[serde(default)]
+    pub last_failed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub locked_until: Option<DateTime<Utc>>,
 }
 
 impl Credential {
     pub fn new(label: String, secret_type: SecretType, secret_data: Vec<u8>) -> Self {
-        let now = Utc::now();
+        Self::new_with_clock(label, secret_type, secret_data, &SystemClock)
+    }
+
+    /// Like `new`, but takes the creation time from `clock`.
+    pub fn new_with_clock(
+        label: String,
+        secret_type: SecretType,
+        secret_data: Vec<u8>,
+        clock: &dyn Clock,
+    ) -> Self {
+        let now = clock.now();
         Self {
             id: Uuid::new_v4().to_string(),
             label,
             tags: Vec::new(),
             created_at: now,
             updated_at: now,
-            policy_id: None,
+            policy_ids: Vec::new(),
             secret: CredentialSecret {
                 type_: secret_type,
                 data: secret_data,
             },
             usage_counter: 0,
+            last_used_at: None,
+            access_history: Vec::new(),
+            failed_attempts: 0,
+            last_failed_at: None,
+            locked_until: None,
         }
     }
+
+    /// The end of the current lock, if the credential is locked at `now`.
+    pub fn locked_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.locked_until.filter(|until| *until > now)
+    }
+}
+
+/// Reads `policy_ids`, also accepting the single `policy_id` of older stores.
+fn one_or_many<'de, D: Deserializer<'de>>(de: D) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match Option::<OneOrMany>::deserialize(de)? {
+        None => Vec::new(),
+        Some(OneOrMany::One(id)) => vec![id],
+        Some(OneOrMany::Many(ids)) => ids,
+    })
 }
 
+/// Why a policy applies to a credential.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyBinding {
+    Direct,       // listed in the credential's `policy_ids`
+    Tag(String),  // bound to one of the credential's tags
+    StoreDefault, // the store's default policy
+}
+
+/// A policy the store applies to credentials on top of their own.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DefaultPolicy {
+    pub policy_id: String,
+    pub mode: DefaultMode,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DefaultMode {
+    Fallback, // only for credentials that no other policy applies to
+    Floor,    // for every credential, in addition to its own policies
+}
+
+/// Current on-disk format version.
+///
+/// Version 1 encoded the payload with bincode; version 2 encodes it as JSON so
+/// that tagged policy hooks round-trip and new fields can be added with defaults.
+pub const STORE_VERSION: u32 = 2;
+
 #[derive(Serialize, Deserialize)]
 struct StoreHeader {
     version: u32,
@@ -76,9 +158,20 @@ impl AuditEntry {
         target_type: impl Into<String>,
         target_id: impl Into<String>,
         details: impl Into<String>,
+    ) -> Self {
+        Self::new_with_clock(action, target_type, target_id, details, &SystemClock)
+    }
+
+    /// Like `new`, but takes the timestamp from `clock`.
+    pub fn new_with_clock(
+        action: impl Into<String>,
+        target_type: impl Into<String>,
+        target_id: impl Into<String>,
+        details: impl Into<String>,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
-            timestamp: Utc::now(),
+            timestamp: clock.now(),
             action: action.into(),
             target_type: target_type.into(),
             target_id: target_id.into(),
@@ -87,12 +180,33 @@ impl AuditEntry {
     }
 }
 
+/// A stored version of a policy and the audit entry that produced it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PolicyRevision {
+    pub version: u32,
+    pub recorded_at: DateTime<Utc>,
+    pub policy: Policy,
+    pub audit: AuditEntry,
+}
+
 #[derive(Serialize, Deserialize)]
-struct StorePayload {
-    credentials: HashMap<String, Credential>,
-    policies: HashMap<String, Policy>,
+pub(crate) struct StorePayload {
+    pub(crate) credentials: HashMap<String, Credential>,
+    pub(crate) policies: HashMap<String, Policy>,
     #[serde(default)]
-    audit_logs: Vec<AuditEntry>,
+    pub(crate) audit_logs: Vec<AuditEntry>,
+    #[serde(default)]
+    pub(crate) calendars: HashMap<String, Calendar>,
+    #[serde(default)]
+    pub(crate) policy_history: HashMap<String, Vec<PolicyRevision>>, // oldest first
+    /// The latest time the store's clock has read; a later read that falls
+    /// behind it means the clock was turned back.
+    #[serde(default)]
+    pub(crate) time_high_water: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) tag_bindings: HashMap<String, Vec<String>>, // tag -> policy ids
+    #[serde(default)]
+    pub(crate) default_policy: Option<DefaultPolicy>,
 }
 
 pub struct SecretStore {
@@ -102,10 +216,27 @@ pub struct SecretStore {
     credentials: HashMap<String, Credential>,
     policies: HashMap<String, Policy>,
     audit_logs: Vec<AuditEntry>,
+    calendars: HashMap<String, Calendar>,
+    policy_history: HashMap<String, Vec<PolicyRevision>>,
+    clock: Arc<dyn Clock>,
+    time_high_water: Option<DateTime<Utc>>,
+    rollback_reported: bool,
+    time_source: Option<Arc<dyn TimeSource>>,
+    tag_bindings: HashMap<String, Vec<String>>,
+    default_policy: Option<DefaultPolicy>,
 }
 
 impl SecretStore {
     pub fn init(path: impl AsRef<Path>, passphrase: &Secret) -> Result<Self> {
+        Self::init_with_clock(path, passphrase, Arc::new(SystemClock))
+    }
+
+    /// Like `init`, but reads the time from `clock` for timestamps and usage tracking.
+    pub fn init_with_clock(
+        path: impl AsRef<Path>,
+        passphrase: &Secret,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self> {
         let (master_key, salt) = MasterKey::derive_from_passphrase(passphrase, None)?;
 
         let store = Self {
@@ -114,12 +245,21 @@ impl SecretStore {
             salt,
             credentials: HashMap::new(),
             policies: HashMap::new(),
-            audit_logs: vec![AuditEntry::new(
+            audit_logs: vec![AuditEntry::new_with_clock(
                 "init",
                 "system",
                 "store",
                 "Store initialized",
+                clock.as_ref(),
             )],
+            calendars: HashMap::new(),
+            policy_history: HashMap::new(),
+            clock,
+            time_high_water: None,
+            rollback_reported: false,
+            time_source: None,
+            tag_bindings: HashMap::new(),
+            default_policy: None,
         };
 
         store.save()?;
@@ -127,6 +267,15 @@ impl SecretStore {
     }
 
     pub fn open(path: impl AsRef<Path>, passphrase: &Secret) -> Result<Self> {
+        Self::open_with_clock(path, passphrase, Arc::new(SystemClock))
+    }
+
+    /// Like `open`, but reads the time from `clock` for timestamps and usage tracking.
+    pub fn open_with_clock(
+        path: impl AsRef<Path>,
+        passphrase: &Secret,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
         let mut file = File::open(&path).map_err(Error::Io)?;
 
@@ -146,21 +295,191 @@ impl SecretStore {
         let master_key = MasterKey::derive_from_passphrase(passphrase, Some(&header.salt))?.0;
 
         let payload_bytes = master_key.decrypt(&encrypted_payload, &header_bytes)?;
-        let payload: StorePayload = bincode::deserialize(&payload_bytes)?;
+        let payload: StorePayload = match header.version {
+            1 => legacy::read_payload_v1(&payload_bytes)?,
+            STORE_VERSION => serde_json::from_slice(&payload_bytes)?,
+            v => return Err(Error::Store(format!("Unsupported store version {}", v))),
+        };
 
-        Ok(Self {
+        let mut store = Self {
             path,
             master_key,
             salt: header.salt,
             credentials: payload.credentials,
             policies: payload.policies,
             audit_logs: payload.audit_logs,
-        })
+            calendars: payload.calendars,
+            policy_history: payload.policy_history,
+            clock,
+            time_high_water: payload.time_high_water,
+            rollback_reported: false,
+            time_source: None,
+            tag_bindings: payload.tag_bindings,
+            default_policy: payload.default_policy,
+        };
+        store.seed_policy_history();
+
+        // A rolled-back clock doesn't stop the store from opening, so it can
+        // still be inspected; it is logged here and refused at evaluation.
+        let tolerance = store
+            .policies
+            .values()
+            .map(|p| p.clock_skew_secs)
+            .min()
+            .unwrap_or(Policy::default().clock_skew_secs);
+        let now = store.clock.now();
+        match store.check_clock(now, tolerance) {
+            Err(Error::ClockRollback { .. }) => {}
+            other => other?,
+        }
+        Ok(store)
+    }
+
+    /// Records the current version of policies saved before history was kept,
+    /// dated by the audit entry that last added them.
+    fn seed_policy_history(&mut self) {
+        for policy in self.policies.values() {
+            if self.policy_history.contains_key(&policy.id) {
+                continue;
+            }
+            let audit = self
+                .audit_logs
+                .iter()
+                .rev()
+                .find(|a| {
+                    a.target_type == "policy" && a.target_id == policy.id && a.action == "add"
+                })
+                .cloned()
+                .unwrap_or_else(|| {
+                    AuditEntry::new_with_clock(
+                        "add",
+                        "policy",
+                        &policy.id,
+                        "Recorded from existing policy",
+                        self.clock.as_ref(),
+                    )
+                });
+            let revision = PolicyRevision {
+                version: policy.version,
+                recorded_at: audit.timestamp,
+                policy: policy.clone(),
+                audit,
+            };
+            self.policy_history
+                .insert(policy.id.clone(), vec![revision]);
+        }
+    }
+
+    /// The clock this store reads the time from.
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// The latest time this store has read from its clock.
+    pub fn time_high_water(&self) -> Option<DateTime<Utc>> {
+        self.time_high_water
+    }
+
+    /// Moves the high-water mark back to the clock's current reading and
+    /// returns the old mark.
+    ///
+    /// For recovering after the clock ran ahead, e.g. a bad RTC reading: the
+    /// mark never moves back on its own, so until it is reset every evaluation
+    /// fails with `Error::ClockRollback`. The reset is audit-logged.
+    pub fn reset_time_high_water(&mut self) -> Result<Option<DateTime<Utc>>> {
+        let previous = self.time_high_water;
+        let now = self.clock.now();
+        self.time_high_water = Some(now);
+        self.rollback_reported = false;
+        let details = match previous {
+            Some(previous) => format!("Time high-water mark reset from {} to {}", previous, now),
+            None => format!("Time high-water mark set to {}", now),
+        };
+        self.audit("clock_reset", "system", "clock", details);
+        self.save()?;
+        Ok(previous)
+    }
+
+    /// Compares `now` with the high-water mark, failing with
+    /// `Error::ClockRollback` when it has fallen behind by more than
+    /// `tolerance_secs`. The first rollback seen by this handle is audit-logged.
+    ///
+    /// The mark itself only advances from the store's clock, so a `now` that
+    /// looks ahead does not move it.
+    fn check_clock(&mut self, now: DateTime<Utc>, tolerance_secs: u64) -> Result<()> {
+        if let Some(high_water) = self.time_high_water {
+            if shifted(now, &[seconds(tolerance_secs)]).is_some_and(|t| t < high_water) {
+                if !self.rollback_reported {
+                    self.rollback_reported = true;
+                    let details = format!(
+                        "Clock reads {}, {}s behind the latest recorded time {}",
+                        now,
+                        (high_water - now).num_seconds(),
+                        high_water
+                    );
+                    self.audit("clock_rollback", "system", "clock", details);
+                    self.save()?;
+                }
+                return Err(Error::ClockRollback { now, high_water });
+            }
+        }
+        self.time_high_water = self.time_high_water.max(Some(self.clock.now()));
+        Ok(())
+    }
+
+    /// Sets where to verify the time for policies with `require_trusted_time`.
+    /// Without one, those policies deny every evaluation.
+    pub fn set_time_source(&mut self, source: Option<Arc<dyn TimeSource>>) {
+        self.time_source = source;
+    }
+
+    /// Checks `now` against a verified reading from the time source, allowing
+    /// for the policy's skew and the reading's uncertainty.
+    fn verify_time(&self, now: DateTime<Utc>, skew_secs: u64) -> Result<()> {
+        let source = self.time_source.as_ref().ok_or_else(|| {
+            Error::UntrustedTime(
+                "the policy requires trusted time but no time source is configured".to_string(),
+            )
+        })?;
+        let reading = source.read()?;
+        if !reading.verified {
+            return Err(Error::UntrustedTime(format!(
+                "{} cannot verify the time",
+                reading.source
+            )));
+        }
+        let drift = (now - reading.time).abs();
+        let allowed = seconds(skew_secs).checked_add(&reading.uncertainty);
+        if allowed.is_some_and(|allowed| drift > allowed) {
+            return Err(Error::UntrustedTime(format!(
+                "local clock is {}s off the time from {}",
+                drift.num_seconds(),
+                reading.source
+            )));
+        }
+        Ok(())
+    }
+
+    fn audit(
+        &mut self,
+        action: &str,
+        target_type: &str,
+        target_id: &str,
+        details: impl Into<String>,
+    ) {
+        let entry = AuditEntry::new_with_clock(
+            action,
+            target_type,
+            target_id,
+            details,
+            self.clock.as_ref(),
+        );
+        self.audit_logs.push(entry);
     }
 
     pub fn save(&self) -> Result<()> {
         let header = StoreHeader {
-            version: 1,
+            version: STORE_VERSION,
             salt: self.salt.clone(),
         };
 
@@ -171,8 +490,13 @@ impl SecretStore {
             credentials: self.credentials.clone(),
             policies: self.policies.clone(),
             audit_logs: self.audit_logs.clone(),
+            calendars: self.calendars.clone(),
+            policy_history: self.policy_history.clone(),
+            time_high_water: self.time_high_water.max(Some(self.clock.now())),
+            tag_bindings: self.tag_bindings.clone(),
+            default_policy: self.default_policy.clone(),
         };
-        let payload_bytes = bincode::serialize(&payload)?;
+        let payload_bytes = serde_json::to_vec(&payload)?;
 
         let encrypted_payload = self.master_key.encrypt(&payload_bytes, &header_bytes)?;
 
@@ -191,15 +515,128 @@ impl SecretStore {
         Ok(())
     }
 
+    /// Adds or replaces a policy, keeping the previous versions in its history.
+    ///
+    /// Versions only move forward: a policy whose version is not newer than the
+    /// latest recorded one is stored as the next version.
     pub fn add_policy(&mut self, policy: Policy) -> Result<()> {
-        self.audit_logs.push(AuditEntry::new(
-            "add",
-            "policy",
-            &policy.id,
-            format!("Policy added/updated: version {}", policy.version),
-        ));
-        self.policies.insert(policy.id.clone(), policy);
-        self.save()
+        self.put_policy(policy, None).map(|_| ())
+    }
+
+    /// Stores a policy as a new version; `restored` is the version a rollback copies.
+    fn put_policy(&mut self, mut policy: Policy, restored: Option<u32>) -> Result<u32> {
+        if let Some(latest) = self.latest_policy_version(&policy.id) {
+            policy.version = policy.version.max(latest + 1);
+        }
+
+        // Check the policy as it will be evaluated, along with every policy that
+        // inherits from it, since they change too.
+        let mut policies = self.policies.clone();
+        policies.insert(policy.id.clone(), policy.clone());
+        for id in policies.keys() {
+            let (resolved, chain) = resolve(&policies, id)?.expect("policy exists");
+            if chain.contains(&policy.id) {
+                self.check_resolved(&resolved)?;
+            }
+        }
+
+        let audit = match restored {
+            Some(from) => AuditEntry::new_with_clock(
+                "rollback",
+                "policy",
+                &policy.id,
+                format!(
+                    "Policy rolled back to version {} as version {}",
+                    from, policy.version
+                ),
+                self.clock.as_ref(),
+            ),
+            None => AuditEntry::new_with_clock(
+                "add",
+                "policy",
+                &policy.id,
+                format!("Policy added/updated: version {}", policy.version),
+                self.clock.as_ref(),
+            ),
+        };
+        let version = policy.version;
+        self.policy_history
+            .entry(policy.id.clone())
+            .or_default()
+            .push(PolicyRevision {
+                version,
+                recorded_at: audit.timestamp,
+                policy,
+                audit: audit.clone(),
+            });
+        self.audit_logs.push(audit);
+        self.policies = policies;
+        self.save()?;
+        Ok(version)
+    }
+
+    fn latest_policy_version(&self, id: &str) -> Option<u32> {
+        let recorded = self
+            .policy_history
+            .get(id)
+            .and_then(|h| h.iter().map(|r| r.version).max());
+        let current = self.policies.get(id).map(|p| p.version);
+        recorded.max(current)
+    }
+
+    /// Every recorded version of a policy, oldest first. History is kept after
+    /// the policy is removed, so it can be rolled back into place.
+    pub fn policy_history(&self, id: &str) -> &[PolicyRevision] {
+        self.policy_history.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn get_policy_version(&self, id: &str, version: u32) -> Option<&Policy> {
+        self.policy_history(id)
+            .iter()
+            .find(|r| r.version == version)
+            .map(|r| &r.policy)
+    }
+
+    /// Makes a copy of an earlier version the current policy, recorded as a new
+    /// version. Returns the new version number.
+    pub fn rollback_policy(&mut self, id: &str, version: u32) -> Result<u32> {
+        let policy = self
+            .get_policy_version(id, version)
+            .cloned()
+            .ok_or_else(|| Error::Store(format!("Policy '{}' has no version {}", id, version)))?;
+        self.put_policy(policy, Some(version))
+    }
+
+    /// Rejects a resolved policy that could never be evaluated or satisfied.
+    fn check_resolved(&self, policy: &Policy) -> Result<()> {
+        // Policies in an unknown timezone deny every access, so reject them here.
+        policy.tz()?;
+        for hook in &policy.hooks {
+            hook.validate()?;
+        }
+        let errors: Vec<String> = policy
+            .validate()
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| format!("{}: {}", policy.id, d))
+            .collect();
+        if !errors.is_empty() {
+            return Err(Error::InvalidPolicy(errors.join("; ")));
+        }
+        if let Some(name) = policy
+            .calendar_names()
+            .into_iter()
+            .find(|name| !self.calendars.contains_key(*name))
+        {
+            return Err(Error::InvalidPolicy(format!("unknown calendar '{}'", name)));
+        }
+        Ok(())
+    }
+
+    /// Returns the policy with its `extends` chain applied, or `None` if no
+    /// policy has this id.
+    pub fn resolve_policy(&self, id: &str) -> Result<Option<Policy>> {
+        Ok(resolve(&self.policies, id)?.map(|(policy, _)| policy))
     }
 
     pub fn get_policy(&self, id: &str) -> Option<&Policy> {
@@ -207,9 +644,44 @@ impl SecretStore {
     }
 
     pub fn remove_policy(&mut self, id: &str) -> Result<()> {
+        if let Some(derived) = self
+            .policies
+            .values()
+            .find(|p| p.extends.as_ref().is_some_and(|e| e.policy == id))
+        {
+            return Err(Error::Store(format!(
+                "Policy '{}' is extended by policy '{}'",
+                id, derived.id
+            )));
+        }
+        if self
+            .default_policy
+            .as_ref()
+            .is_some_and(|d| d.policy_id == id)
+        {
+            return Err(Error::Store(format!(
+                "Policy '{}' is the store's default policy",
+                id
+            )));
+        }
+        if let Some(cred) = self
+            .credentials
+            .values()
+            .find(|c| c.policy_ids.iter().any(|p| p == id))
+        {
+            return Err(Error::Store(format!(
+                "Policy '{}' is used by credential '{}'",
+                id, cred.id
+            )));
+        }
+        if let Some(tag) = self.tags_bound_to(id).first() {
+            return Err(Error::Store(format!(
+                "Policy '{}' is bound to tag '{}'",
+                id, tag
+            )));
+        }
         if self.policies.remove(id).is_some() {
-            self.audit_logs
-                .push(AuditEntry::new("remove", "policy", id, "Policy removed"));
+            self.audit("remove", "policy", id, "Policy removed");
             self.save()
         } else {
             Ok(())
@@ -220,17 +692,290 @@ impl SecretStore {
         self.policies.values().collect()
     }
 
+    /// Binds a policy to a tag, so that it applies to every credential with
+    /// that tag in addition to the credential's own policies.
+    pub fn bind_policy(&mut self, tag: &str, policy_id: &str) -> Result<()> {
+        if !self.policies.contains_key(policy_id) {
+            return Err(Error::NotFound(policy_id.to_string()));
+        }
+        let bound = self.tag_bindings.entry(tag.to_string()).or_default();
+        if bound.iter().any(|id| id == policy_id) {
+            return Ok(());
+        }
+        bound.push(policy_id.to_string());
+        self.audit(
+            "bind",
+            "policy",
+            policy_id,
+            format!("Policy bound to tag '{}'", tag),
+        );
+        self.save()
+    }
+
+    pub fn unbind_policy(&mut self, tag: &str, policy_id: &str) -> Result<()> {
+        let Some(bound) = self.tag_bindings.get_mut(tag) else {
+            return Ok(());
+        };
+        let before = bound.len();
+        bound.retain(|id| id != policy_id);
+        if bound.len() == before {
+            return Ok(());
+        }
+        if bound.is_empty() {
+            self.tag_bindings.remove(tag);
+        }
+        self.audit(
+            "unbind",
+            "policy",
+            policy_id,
+            format!("Policy unbound from tag '{}'", tag),
+        );
+        self.save()
+    }
+
+    /// Tags and the policies bound to them, in the order they were bound.
+    pub fn tag_bindings(&self) -> &HashMap<String, Vec<String>> {
+        &self.tag_bindings
+    }
+
+    /// The tags a policy is bound to, sorted.
+    pub fn tags_bound_to(&self, policy_id: &str) -> Vec<&str> {
+        let mut tags: Vec<&str> = self
+            .tag_bindings
+            .iter()
+            .filter(|(_, ids)| ids.iter().any(|id| id == policy_id))
+            .map(|(tag, _)| tag.as_str())
+            .collect();
+        tags.sort_unstable();
+        tags
+    }
+
+    /// Sets the policy applied to credentials beyond their own: as a fallback
+    /// for credentials with no other policy, or as a floor for all of them.
+    pub fn set_default_policy(&mut self, policy_id: &str, mode: DefaultMode) -> Result<()> {
+        if !self.policies.contains_key(policy_id) {
+            return Err(Error::NotFound(policy_id.to_string()));
+        }
+        let details = match mode {
+            DefaultMode::Fallback => "Store default policy set (fallback)",
+            DefaultMode::Floor => "Store default policy set (floor)",
+        };
+        self.default_policy = Some(DefaultPolicy {
+            policy_id: policy_id.to_string(),
+            mode,
+        });
+        self.audit("set_default", "policy", policy_id, details);
+        self.save()
+    }
+
+    pub fn clear_default_policy(&mut self) -> Result<()> {
+        let Some(previous) = self.default_policy.take() else {
+            return Ok(());
+        };
+        self.audit(
+            "clear_default",
+            "policy",
+            &previous.policy_id,
+            "Store default policy cleared",
+        );
+        self.save()
+    }
+
+    pub fn default_policy(&self) -> Option<&DefaultPolicy> {
+        self.default_policy.as_ref()
+    }
+
+    /// The policies that apply to a credential: its own `policy_ids`, then
+    /// those bound to its tags, then the store default if it is a floor or
+    /// none of the others exist. Each appears once; access requires all of them.
+    pub fn effective_policies(&self, cred: &Credential) -> Vec<(String, PolicyBinding)> {
+        let mut out: Vec<(String, PolicyBinding)> = Vec::new();
+        let direct = cred.policy_ids.iter().map(|id| (id, PolicyBinding::Direct));
+        let tagged = cred.tags.iter().flat_map(|tag| {
+            self.tag_bindings
+                .get(tag)
+                .into_iter()
+                .flatten()
+                .map(move |id| (id, PolicyBinding::Tag(tag.clone())))
+        });
+        for (id, binding) in direct.chain(tagged) {
+            if !out.iter().any(|(seen, _)| seen == id) {
+                out.push((id.clone(), binding));
+            }
+        }
+        if let Some(default) = &self.default_policy {
+            let applies = match default.mode {
+                DefaultMode::Fallback => !out.iter().any(|(id, _)| self.policies.contains_key(id)),
+                DefaultMode::Floor => !out.iter().any(|(id, _)| *id == default.policy_id),
+            };
+            if applies {
+                out.push((default.policy_id.clone(), PolicyBinding::StoreDefault));
+            }
+        }
+        out
+    }
+
+    /// Adds a calendar, replacing any existing calendar with the same name.
+    pub fn add_calendar(&mut self, calendar: Calendar) -> Result<()> {
+        calendar.validate()?;
+        self.audit(
+            "add",
+            "calendar",
+            &calendar.name,
+            format!("Calendar added/updated: {} entries", calendar.entries.len()),
+        );
+        self.calendars.insert(calendar.name.clone(), calendar);
+        self.save()
+    }
+
+    pub fn get_calendar(&self, name: &str) -> Option<&Calendar> {
+        self.calendars.get(name)
+    }
+
+    pub fn list_calendars(&self) -> Vec<&Calendar> {
+        self.calendars.values().collect()
+    }
+
+    /// Removes a calendar. Fails while a policy still references it, since
+    /// the policy would then deny access everywhere.
+    pub fn remove_calendar(&mut self, name: &str) -> Result<()> {
+        if let Some(policy) = self
+            .policies
+            .values()
+            .find(|p| p.calendar_names().contains(&name))
+        {
+            return Err(Error::Store(format!(
+                "Calendar '{}' is used by policy '{}'",
+                name, policy.id
+            )));
+        }
+        if self.calendars.remove(name).is_some() {
+            self.audit("remove", "calendar", name, "Calendar removed");
+            self.save()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The resolved effective policies of a credential. An id with no stored
+    /// policy is an error, so the credential fails closed.
+    fn credential_policies(&self, cred: &Credential) -> Result<Vec<Policy>> {
+        let mut policies = Vec::new();
+        for (id, _) in self.effective_policies(cred) {
+            let policy = self.resolve_policy(&id)?.ok_or_else(|| {
+                Error::InvalidPolicy(format!(
+                    "credential '{}' uses unknown policy '{}'",
+                    cred.id, id
+                ))
+            })?;
+            policies.push(policy);
+        }
+        Ok(policies)
+    }
+
+    /// Evaluates every policy at `now`. The result is the first denial, or the
+    /// first policy's evaluation if all accept. With several policies,
+    /// `details["policy"]` names the one it came from.
+    fn evaluate_policies(
+        &self,
+        cred: &Credential,
+        policies: &[Policy],
+        now: DateTime<Utc>,
+    ) -> (usize, PolicyEvaluation) {
+        let mut evals: Vec<PolicyEvaluation> = policies
+            .iter()
+            .map(|policy| policy.evaluate(&self.context_for(cred, policy, now)))
+            .collect();
+        let index = evals
+            .iter()
+            .position(|eval| eval.verdict != Verdict::Accept)
+            .unwrap_or(0);
+        // When every policy grants access, surface the most urgent warning:
+        // a grace period before an upcoming expiry, the soonest first.
+        let warning = evals
+            .iter()
+            .filter_map(|eval| eval.warning.clone())
+            .min_by_key(|warning| match warning {
+                Warning::InGracePeriod { until, .. } => (0, *until),
+                Warning::ExpiresSoon { at } => (1, *at),
+            });
+        let mut eval = evals.swap_remove(index);
+        if eval.verdict == Verdict::Accept {
+            if let Some(warning) = warning {
+                eval.details
+                    .insert("warning".to_string(), warning.to_string());
+                eval.warning = Some(warning);
+            }
+        }
+        if policies.len() > 1 {
+            eval.details
+                .insert("policy".to_string(), policies[index].id.clone());
+        }
+        (index, eval)
+    }
+
+    /// The next time the combined verdict of `policies` switches between
+    /// granting and denying, found by stepping through each policy's own
+    /// transitions.
+    fn combined_transition(
+        &self,
+        cred: &Credential,
+        policies: &[Policy],
+        from: DateTime<Utc>,
+    ) -> Option<Transition> {
+        if let [policy] = policies {
+            return policy.next_transition(&self.context_for(cred, policy, from));
+        }
+        const MAX_STEPS: usize = 64;
+        let granted = |eval: &PolicyEvaluation| eval.verdict == Verdict::Accept;
+        let initially = granted(&self.evaluate_policies(cred, policies, from).1);
+        let mut at = from;
+        for _ in 0..MAX_STEPS {
+            let next = policies
+                .iter()
+                .filter_map(|p| p.next_transition(&self.context_for(cred, p, at)))
+                .map(|t| t.at)
+                .filter(|t| *t > at)
+                .min()?;
+            let eval = self.evaluate_policies(cred, policies, next).1;
+            if granted(&eval) != initially {
+                return Some(Transition {
+                    at: next,
+                    verdict: eval.verdict,
+                });
+            }
+            at = next;
+        }
+        None
+    }
+
+    /// Builds the evaluation context for a credential, including the calendars its policy uses.
+    fn context_for(
+        &self,
+        cred: &Credential,
+        policy: &Policy,
+        now: DateTime<Utc>,
+    ) -> EvaluationContext {
+        let mut ctx = EvaluationContext::for_credential(cred, now);
+        for name in policy.calendar_names() {
+            if let Some(calendar) = self.calendars.get(name) {
+                ctx.calendars.insert(name.to_string(), calendar.clone());
+            }
+        }
+        ctx
+    }
+
     pub fn get_audit_logs(&self) -> &[AuditEntry] {
         &self.audit_logs
     }
 
     pub fn add_credential(&mut self, cred: Credential) -> Result<()> {
-        self.audit_logs.push(AuditEntry::new(
+        self.audit(
             "add",
             "credential",
             &cred.id,
             format!("Credential added: {}", cred.label),
-        ));
+        );
         self.credentials.insert(cred.id.clone(), cred);
         self.save()
     }
@@ -245,33 +990,242 @@ impl SecretStore {
 
     pub fn remove_credential(&mut self, id: &str) -> Result<()> {
         if self.credentials.remove(id).is_some() {
-            self.audit_logs.push(AuditEntry::new(
-                "remove",
-                "credential",
-                id,
-                "Credential removed",
-            ));
+            self.audit("remove", "credential", id, "Credential removed");
             self.save()
         } else {
             Ok(())
         }
     }
 
+    /// Evaluates the effective policies of a credential at `now`; access is
+    /// granted only if every one accepts.
+    ///
+    /// Returns `None` if no policy applies. Every denial is recorded in the
+    /// audit log. Denials by a policy's rules are counted against the
+    /// credential, but `Disabled` and `RateLimited` are not; once a policy's
+    /// `lockout` threshold is reached the credential is locked (for the longest
+    /// lock any policy asks for), and later calls return `Verdict::Locked`
+    /// without evaluating the policies until the lock ends or
+    /// `unlock_credential` is called. An accepted evaluation resets the count.
+    ///
+    /// Fails with `Error::ClockRollback` if `now` is behind the time high-water
+    /// mark, and with `Error::UntrustedTime` if a policy requires trusted time
+    /// and the time source cannot confirm `now`. Neither counts as a denial.
+    pub fn evaluate_credential(
+        &mut self,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<PolicyEvaluation>> {
+        let cred = self
+            .credentials
+            .get(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+        let policies = self.credential_policies(cred)?;
+        let Some(skew) = policies.iter().map(|p| p.clock_skew_secs).min() else {
+            return Ok(None);
+        };
+
+        self.check_clock(now, skew)?;
+        let trusted_skew = policies
+            .iter()
+            .filter(|p| p.require_trusted_time)
+            .map(|p| p.clock_skew_secs)
+            .min();
+        if let Some(skew) = trusted_skew {
+            if let Err(err) = self.verify_time(now, skew) {
+                self.audit("deny", "credential", id, format!("Access denied: {}", err));
+                self.save()?;
+                return Err(err);
+            }
+        }
+        let cred = &self.credentials[id];
+        if let Some(until) = cred.locked_at(now) {
+            let reason = Reason::LockedOut {
+                until,
+                failures: cred.failed_attempts,
+            };
+            let details = format!("Access denied: {} until {}", reason, until);
+            self.audit("deny", "credential", id, details);
+            self.save()?;
+            return Ok(Some(PolicyEvaluation {
+                verdict: Verdict::Locked { until },
+                matched_hooks: Vec::new(),
+                branches: Vec::new(),
+                details: HashMap::from([
+                    ("reason".to_string(), reason.to_string()),
+                    ("locked_until".to_string(), until.to_rfc3339()),
+                ]),
+                reason: Some(reason),
+                trace: Vec::new(),
+                warning: None,
+            }));
+        }
+
+        let (index, eval) = self.evaluate_policies(cred, &policies, now);
+        if eval.verdict == Verdict::Accept {
+            let in_grace = matches!(eval.warning, Some(Warning::InGracePeriod { .. }));
+            let had_failures = cred.failed_attempts > 0;
+            if in_grace {
+                let details = format!(
+                    "Access allowed in grace period: {}",
+                    eval.warning.as_ref().expect("checked above")
+                );
+                self.audit("grace", "credential", id, details);
+            }
+            if had_failures || in_grace {
+                let cred = self.credentials.get_mut(id).expect("checked above");
+                cred.failed_attempts = 0;
+                cred.locked_until = None;
+                self.save()?;
+            }
+            return Ok(Some(eval));
+        }
+
+        let details = match (&eval.verdict, &eval.reason) {
+            (Verdict::Disabled, _) => {
+                format!(
+                    "Access blocked: policy '{}' is disabled",
+                    policies[index].id
+                )
+            }
+            (_, Some(reason)) => format!("Access denied: {}", reason),
+            (verdict, None) => format!("Access denied: {:?}", verdict),
+        };
+        self.audit("deny", "credential", id, details);
+
+        // An admin switch or a throttled caller is not a failed guess.
+        if matches!(
+            eval.verdict,
+            Verdict::Disabled | Verdict::RateLimited { .. }
+        ) {
+            self.save()?;
+            return Ok(Some(eval));
+        }
+        let cred = self.credentials.get_mut(id).expect("checked above");
+        cred.failed_attempts = cred.failed_attempts.saturating_add(1);
+        cred.last_failed_at = Some(now);
+        let lock = policies
+            .iter()
+            .filter_map(|p| p.lockout.as_ref()?.lock_duration(cred.failed_attempts))
+            .max();
+        if let Some(duration) = lock {
+            let until = shifted(now, &[duration]).unwrap_or(DateTime::<Utc>::MAX_UTC);
+            cred.locked_until = Some(until);
+            let details = format!(
+                "Locked until {} after {} failed attempts",
+                until, cred.failed_attempts
+            );
+            self.audit("lock", "credential", id, details);
+        }
+        self.save()?;
+        Ok(Some(eval))
+    }
+
+    /// Clears a credential's lock and failed-attempt count.
+    pub fn unlock_credential(&mut self, id: &str) -> Result<()> {
+        let cred = self
+            .credentials
+            .get_mut(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+        let details = format!(
+            "Credential unlocked; cleared {} failed attempt(s)",
+            cred.failed_attempts
+        );
+        cred.failed_attempts = 0;
+        cred.locked_until = None;
+        self.audit("unlock", "credential", id, details);
+        self.save()
+    }
+
+    /// Returns when the credential's effective policies next switch between
+    /// granting and denying access, or `None` if none apply or access never
+    /// changes. A locked credential is denied at least until its lock ends.
+    pub fn next_transition(&self, id: &str, now: DateTime<Utc>) -> Result<Option<Transition>> {
+        let cred = self
+            .credentials
+            .get(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+        let policies = self.credential_policies(cred)?;
+        if policies.is_empty() {
+            return Ok(None);
+        }
+        let Some(until) = cred.locked_at(now) else {
+            return Ok(self.combined_transition(cred, &policies, now));
+        };
+
+        if self.evaluate_policies(cred, &policies, until).1.verdict == Verdict::Accept {
+            return Ok(Some(Transition {
+                at: until,
+                verdict: Verdict::Accept,
+            }));
+        }
+        Ok(self.combined_transition(cred, &policies, until))
+    }
+
     pub fn increment_usage(&mut self, id: &str) -> Result<()> {
+        let window = match self.credentials.get(id) {
+            Some(cred) => self
+                .credential_policies(cred)?
+                .iter()
+                .filter_map(|p| p.rate_limit_window_secs())
+                .max(),
+            None => None,
+        };
+        let now = self.clock.now();
         if let Some(cred) = self.credentials.get_mut(id) {
             cred.usage_counter += 1;
-            cred.updated_at = Utc::now();
+            cred.updated_at = now;
+            cred.last_used_at = Some(now);
+
+            // Keep only the history that the credential's rate limit can still see.
+            match window {
+                Some(secs) => {
+                    if let Some(cutoff) = shifted(now, &[-seconds(secs)]) {
+                        cred.access_history.retain(|t| *t > cutoff);
+                    }
+                    cred.access_history.push(now);
+                }
+                None => cred.access_history.clear(),
+            }
             // We don't necessarily want to log every usage in audit log to avoid bloat,
             // but for security it might be good. Let's log it.
-            self.audit_logs.push(AuditEntry::new(
-                "usage",
-                "credential",
-                id,
-                "Credential accessed",
-            ));
+            self.audit("usage", "credential", id, "Credential accessed");
             self.save()
         } else {
             Err(Error::Store(format!("Credential {} not found", id)))
         }
     }
 }
+
+/// Applies the `extends` chain of policy `id`, returning the resolved policy
+/// and the ids along the chain (starting with `id`).
+fn resolve(policies: &HashMap<String, Policy>, id: &str) -> Result<Option<(Policy, Vec<String>)>> {
+    let Some(mut policy) = policies.get(id) else {
+        return Ok(None);
+    };
+    let mut chain = vec![policy];
+    while let Some(extends) = &policy.extends {
+        if chain.iter().any(|p| p.id == extends.policy) {
+            let path: Vec<&str> = chain.iter().map(|p| p.id.as_str()).collect();
+            return Err(Error::InvalidPolicy(format!(
+                "inheritance cycle: {} -> {}",
+                path.join(" -> "),
+                extends.policy
+            )));
+        }
+        policy = policies.get(&extends.policy).ok_or_else(|| {
+            Error::InvalidPolicy(format!(
+                "policy '{}' extends unknown policy '{}'",
+                policy.id, extends.policy
+            ))
+        })?;
+        chain.push(policy);
+    }
+
+    let ids = chain.iter().map(|p| p.id.clone()).collect();
+    let mut resolved = policy.clone();
+    for derived in chain.iter().rev().skip(1) {
+        resolved = derived.inherit_from(&resolved)?;
+    }
+    Ok(Some((resolved, ids)))
+}
@@ -0,0 +1,292 @@
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Error, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use ed25519_dalek::{Signature, VerifyingKey};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
+
+const DEFAULT_TIMEOUT: StdDuration = StdDuration::from_secs(3);
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800; // seconds from 1900 to 1970
+
+/// Signatures from a `SignedTimeSource` cover this context string, followed by
+/// the request nonce, the midpoint and the radius.
+pub const SIGNED_TIME_CONTEXT: &[u8] = b"timely-pass signed time v1\0";
+
+/// A reading of the current time, and how far it can be trusted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeReading {
+    pub time: DateTime<Utc>,
+    pub uncertainty: Duration, // the true time is within `time ± uncertainty`
+    pub verified: bool,        // false when the reading is just the local clock
+    pub source: String,
+}
+
+/// Somewhere to read the current time from.
+///
+/// Policies with `require_trusted_time` are only evaluated after the store's
+/// time source returns a verified reading that agrees with the local clock.
+pub trait TimeSource: Send + Sync {
+    fn read(&self) -> Result<TimeReading>;
+}
+
+/// The local clock. Its readings are never verified, so it cannot satisfy a
+/// policy that requires trusted time.
+#[derive(Clone)]
+pub struct SystemTimeSource {
+    clock: Arc<dyn Clock>,
+}
+
+impl SystemTimeSource {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self { clock }
+    }
+}
+
+impl Default for SystemTimeSource {
+    fn default() -> Self {
+        Self::new(Arc::new(SystemClock))
+    }
+}
+
+impl TimeSource for SystemTimeSource {
+    fn read(&self) -> Result<TimeReading> {
+        Ok(TimeReading {
+            time: self.clock.now(),
+            uncertainty: Duration::zero(),
+            verified: false,
+            source: "system".to_string(),
+        })
+    }
+}
+
+/// Queries an NTP server with a single SNTP (RFC 4330) request over UDP.
+///
+/// SNTP responses are not authenticated; they prove the server is reachable
+/// and agrees with the local clock, not that the network is honest.
+#[derive(Clone)]
+pub struct SntpTimeSource {
+    server: String,
+    timeout: StdDuration,
+    clock: Arc<dyn Clock>,
+}
+
+impl SntpTimeSource {
+    /// `server` is a `host:port` address, e.g. `pool.ntp.org:123`.
+    pub fn new(server: impl Into<String>) -> Self {
+        Self {
+            server: server.into(),
+            timeout: DEFAULT_TIMEOUT,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: StdDuration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The local clock the server's offset is measured against.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn fail(&self, message: impl std::fmt::Display) -> Error {
+        Error::UntrustedTime(format!("sntp://{}: {}", self.server, message))
+    }
+}
+
+impl TimeSource for SntpTimeSource {
+    fn read(&self) -> Result<TimeReading> {
+        let mut request = [0u8; 48];
+        request[0] = 0b00_100_011; // no leap warning, version 4, client mode
+        let t1 = self.clock.now();
+        request[40..48].copy_from_slice(&to_ntp(t1));
+
+        let response = exchange(&self.server, &request, self.timeout).map_err(|e| self.fail(e))?;
+        let t4 = self.clock.now();
+        if response.len() < 48 {
+            return Err(self.fail("response too short"));
+        }
+        if response[0] & 0b111 != 4 {
+            return Err(self.fail("response is not from a server"));
+        }
+        if response[0] >> 6 == 3 {
+            return Err(self.fail("server clock is not synchronized"));
+        }
+        if response[1] == 0 {
+            return Err(self.fail("server refused the request"));
+        }
+        if response[24..32] != request[40..48] {
+            return Err(self.fail("response does not match the request"));
+        }
+        let t2 = from_ntp(&response[32..40]);
+        let t3 = from_ntp(&response[40..48]);
+
+        let offset = ((t2 - t1) + (t3 - t4)) / 2;
+        let delay = (t4 - t1) - (t3 - t2);
+        Ok(TimeReading {
+            time: t4 + offset,
+            uncertainty: (delay / 2).max(Duration::zero()),
+            verified: true,
+            source: format!("sntp://{}", self.server),
+        })
+    }
+}
+
+/// Asks a server for a signed statement of the time, in the style of Roughtime.
+///
+/// The request is a random 32-byte nonce. The response is the midpoint
+/// (microseconds since the Unix epoch, u64 big-endian), the radius
+/// (microseconds, u32 big-endian) and an Ed25519 signature over
+/// `SIGNED_TIME_CONTEXT || nonce || midpoint || radius`. Only servers holding
+/// the private half of `public_key` can produce a reading this source accepts.
+#[derive(Clone)]
+pub struct SignedTimeSource {
+    server: String,
+    public_key: VerifyingKey,
+    timeout: StdDuration,
+}
+
+impl SignedTimeSource {
+    pub fn new(server: impl Into<String>, public_key: VerifyingKey) -> Self {
+        Self {
+            server: server.into(),
+            public_key,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: StdDuration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn fail(&self, message: impl std::fmt::Display) -> Error {
+        Error::UntrustedTime(format!("signed://{}: {}", self.server, message))
+    }
+}
+
+impl TimeSource for SignedTimeSource {
+    fn read(&self) -> Result<TimeReading> {
+        let nonce: [u8; 32] = rand::random();
+        let sent = Instant::now();
+        let response = exchange(&self.server, &nonce, self.timeout).map_err(|e| self.fail(e))?;
+        let round_trip = Duration::from_std(sent.elapsed()).unwrap_or(Duration::zero());
+        if response.len() != 76 {
+            return Err(self.fail("response has the wrong length"));
+        }
+
+        let midpoint = u64::from_be_bytes(response[0..8].try_into().expect("8 bytes"));
+        let radius = u32::from_be_bytes(response[8..12].try_into().expect("4 bytes"));
+        let signature = Signature::from_slice(&response[12..76]).map_err(|e| self.fail(e))?;
+        self.public_key
+            .verify_strict(&signed_time_message(&nonce, midpoint, radius), &signature)
+            .map_err(|_| self.fail("signature does not verify"))?;
+
+        let time = i64::try_from(midpoint)
+            .ok()
+            .and_then(|micros| Utc.timestamp_micros(micros).single())
+            .ok_or_else(|| self.fail("midpoint is out of range"))?;
+        Ok(TimeReading {
+            time,
+            uncertainty: Duration::microseconds(radius as i64) + round_trip,
+            verified: true,
+            source: format!("signed://{}", self.server),
+        })
+    }
+}
+
+/// The bytes a signed-time server signs for a request with `nonce`.
+pub fn signed_time_message(nonce: &[u8], midpoint_micros: u64, radius_micros: u32) -> Vec<u8> {
+    let mut message = SIGNED_TIME_CONTEXT.to_vec();
+    message.extend_from_slice(nonce);
+    message.extend_from_slice(&midpoint_micros.to_be_bytes());
+    message.extend_from_slice(&radius_micros.to_be_bytes());
+    message
+}
+
+/// Builds a time source from a URL: `system`, `sntp://host:port` or
+/// `signed://host:port#<base64 Ed25519 public key>`.
+pub fn parse_time_source(url: &str) -> Result<Arc<dyn TimeSource>> {
+    use base64::Engine;
+
+    let invalid = |message: &str| Error::UntrustedTime(format!("'{}': {}", url, message));
+    if url == "system" {
+        return Ok(Arc::new(SystemTimeSource::default()));
+    }
+    if let Some(server) = url.strip_prefix("sntp://") {
+        return Ok(Arc::new(SntpTimeSource::new(server)));
+    }
+    if let Some(rest) = url.strip_prefix("signed://") {
+        let (server, key) = rest
+            .split_once('#')
+            .ok_or_else(|| invalid("missing '#<public key>'"))?;
+        let key: [u8; 32] = base64::engine::general_purpose::STANDARD
+            .decode(key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| invalid("public key must be 32 bytes of base64"))?;
+        let key = VerifyingKey::from_bytes(&key).map_err(|_| invalid("invalid public key"))?;
+        return Ok(Arc::new(SignedTimeSource::new(server, key)));
+    }
+    Err(invalid(
+        "expected system, sntp://host:port or signed://host:port#key",
+    ))
+}
+
+/// Sends `request` to each address `server` resolves to until one answers.
+fn exchange(server: &str, request: &[u8], timeout: StdDuration) -> std::io::Result<Vec<u8>> {
+    let mut last_err = None;
+    for addr in server.to_socket_addrs()? {
+        // The local socket has to match the server's address family.
+        let local: SocketAddr = match addr {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let attempt = || {
+            let socket = UdpSocket::bind(local)?;
+            socket.set_read_timeout(Some(timeout))?;
+            socket.connect(addr)?;
+            socket.send(request)?;
+            let mut buf = [0u8; 512];
+            let len = socket.recv(&mut buf)?;
+            Ok(buf[..len].to_vec())
+        };
+        match attempt() {
+            Ok(response) => return Ok(response),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} did not resolve to any address", server),
+        )
+    }))
+}
+
+fn to_ntp(time: DateTime<Utc>) -> [u8; 8] {
+    let secs = (time.timestamp() + NTP_UNIX_OFFSET) as u32; // wraps in era 1, as NTP does
+    let frac = ((time.timestamp_subsec_nanos() as u64) << 32) / 1_000_000_000;
+    let mut out = [0u8; 8];
+    out[..4].copy_from_slice(&secs.to_be_bytes());
+    out[4..].copy_from_slice(&(frac as u32).to_be_bytes());
+    out
+}
+
+fn from_ntp(bytes: &[u8]) -> DateTime<Utc> {
+    let secs = u32::from_be_bytes(bytes[..4].try_into().expect("4 bytes")) as i64;
+    let frac = u32::from_be_bytes(bytes[4..8].try_into().expect("4 bytes")) as u64;
+    let nanos = ((frac * 1_000_000_000) >> 32) as u32;
+    // Timestamps with the top bit clear belong to NTP era 1 (2036 onwards).
+    let secs = if secs & 0x8000_0000 == 0 {
+        secs + (1 << 32)
+    } else {
+        secs
+    };
+    Utc.timestamp_opt(secs - NTP_UNIX_OFFSET, nanos)
+        .single()
+        .unwrap_or_default()
+}
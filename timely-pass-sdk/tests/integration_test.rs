@@ -1,9 +1,27 @@
-use chrono::{Duration, Utc};
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use std::sync::Arc;
 use tempfile::tempdir;
+use timely_pass_sdk::calendar::{Calendar, CalendarEntry};
+use timely_pass_sdk::clock::{Clock, MockClock};
 use timely_pass_sdk::crypto::Secret;
-use timely_pass_sdk::eval::{EvaluationContext, Verdict};
-use timely_pass_sdk::policy::{Hook, Period, Policy};
-use timely_pass_sdk::store::{Credential, SecretStore, SecretType};
+use timely_pass_sdk::dsl;
+use timely_pass_sdk::error::Error;
+use timely_pass_sdk::eval::{EvaluationContext, Reason, Verdict, Warning};
+use timely_pass_sdk::policy::{
+    local_to_utc, Backoff, DisabledMode, Extends, Hook, HookMerge, Lockout, Period, Policy, Window,
+};
+use timely_pass_sdk::store::{
+    Credential, DefaultMode, PolicyBinding, SecretStore, SecretType, STORE_VERSION,
+};
+use timely_pass_sdk::timesource::{self, SignedTimeSource, SntpTimeSource, SystemTimeSource};
+
+/// A clock at the start of 2024, for stores that evaluate at fixed dates in
+/// 2025 without them counting as a clock rollback.
+fn clock_before_2025() -> Arc<MockClock> {
+    Arc::new(MockClock::new(
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+    ))
+}
 
 #[test]
 fn test_store_encryption_and_roundtrip() {
@@ -99,3 +117,2049 @@ fn test_only_for_duration() {
         Verdict::Expired
     ));
 }
+
+#[test]
+fn test_timezone_aware_local_range() {
+    // 09:00-17:00 Berlin wall-clock on the day DST starts (clocks jump 02:00 -> 03:00).
+    let policy = Policy::new("berlin-hours")
+        .with_timezone("Europe/Berlin")
+        .add_hook(Hook::OnlyWithin {
+            period: Period::LocalRange {
+                start: NaiveDate::from_ymd_opt(2026, 3, 29)
+                    .unwrap()
+                    .and_hms_opt(9, 0, 0)
+                    .unwrap(),
+                end: NaiveDate::from_ymd_opt(2026, 3, 29)
+                    .unwrap()
+                    .and_hms_opt(17, 0, 0)
+                    .unwrap(),
+            },
+        });
+
+    // 07:30 UTC is 09:30 CEST.
+    let inside = EvaluationContext {
+        now: Utc.with_ymd_and_hms(2026, 3, 29, 7, 30, 0).unwrap(),
+        ..Default::default()
+    };
+    assert_eq!(policy.evaluate(&inside).verdict, Verdict::Accept);
+
+    // 15:30 UTC is 17:30 CEST, after the range ends, although 15:30 read as a
+    // UTC time would still be inside it.
+    let after = EvaluationContext {
+        now: Utc.with_ymd_and_hms(2026, 3, 29, 15, 30, 0).unwrap(),
+        ..Default::default()
+    };
+    assert!(matches!(
+        policy.evaluate(&after).verdict,
+        Verdict::PolicyViolation(_)
+    ));
+
+    // A wall-clock time inside the DST gap is shifted forward by the gap.
+    let tz = policy.tz().unwrap();
+    let gap = NaiveDate::from_ymd_opt(2026, 3, 29)
+        .unwrap()
+        .and_hms_opt(2, 30, 0)
+        .unwrap();
+    assert_eq!(
+        local_to_utc(&tz, gap),
+        Utc.with_ymd_and_hms(2026, 3, 29, 1, 30, 0).unwrap()
+    );
+}
+
+#[test]
+fn test_unknown_timezone_rejected() {
+    let json = r#"{
+        "id": "bad-tz",
+        "hooks": [],
+        "timezone": "Mars/Olympus_Mons",
+        "clock_skew_secs": 60,
+        "max_attempts": null,
+        "single_use": false,
+        "version": 1
+    }"#;
+    // The file still parses, so validation can report the zone alongside any
+    // other mistakes; the store refuses it.
+    let parsed = Policy::from_json(json).unwrap();
+    let diagnostics = parsed.validate();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, "timezone");
+    assert!(diagnostics[0].message.contains("Mars/Olympus_Mons"));
+    let dir = tempdir().unwrap();
+    let mut store =
+        SecretStore::init(dir.path().join("store.timely"), &Secret::from("pw")).unwrap();
+    assert!(store.add_policy(parsed).is_err());
+
+    let policy = Policy::new("bad-tz").with_timezone("Mars/Olympus_Mons");
+    assert!(policy.tz().is_err());
+    assert!(matches!(
+        policy.evaluate(&EvaluationContext::default()).verdict,
+        Verdict::PolicyViolation(_)
+    ));
+}
+
+#[test]
+fn test_store_roundtrip_with_policy_hooks() {
+    let dir = tempdir().unwrap();
+    let store_path = dir.path().join("store.timely");
+    let passphrase = Secret::new(b"correct-horse-battery-staple".to_vec());
+
+    let policy = Policy::new("hooked")
+        .with_timezone("America/New_York")
+        .add_hook(Hook::OnlyFor {
+            duration_secs: 3600,
+        })
+        .add_hook(Hook::OnlyBefore {
+            period: Period::Instant { value: Utc::now() },
+        });
+
+    {
+        let mut store = SecretStore::init(&store_path, &passphrase).unwrap();
+        store.add_policy(policy.clone()).unwrap();
+        assert!(store
+            .add_policy(Policy::new("bad").with_timezone("Nowhere/Land"))
+            .is_err());
+    }
+
+    let store = SecretStore::open(&store_path, &passphrase).unwrap();
+    assert_eq!(store.get_policy("hooked"), Some(&policy));
+    assert!(store.get_policy("bad").is_none());
+}
+
+#[test]
+fn test_open_version_1_store() {
+    // Written by the bincode-based store before the JSON format, with
+    // passphrase "v1-fixture".
+    let fixture = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/store_v1.timely"
+    );
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    std::fs::copy(fixture, &path).unwrap();
+    let header_version = |path: &std::path::Path| {
+        let bytes = std::fs::read(path).unwrap();
+        u32::from_le_bytes(bytes[4..8].try_into().unwrap())
+    };
+    assert_eq!(header_version(&path), 1);
+
+    let passphrase = Secret::from("v1-fixture");
+    let store = SecretStore::open(&path, &passphrase).unwrap();
+    let cred = store.get_credential("db-cred").unwrap();
+    assert_eq!(cred.label, "db");
+    assert_eq!(cred.tags, ["prod"]);
+    assert_eq!(cred.policy_ids, ["legacy"]);
+    assert_eq!(cred.usage_counter, 3);
+    assert_eq!(cred.secret.type_, SecretType::Password);
+    assert_eq!(cred.secret.data, b"hunter2");
+
+    let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    let policy = store.get_policy("legacy").unwrap();
+    assert_eq!(
+        policy.hooks,
+        [
+            Hook::OnlyWithin {
+                period: Period::Range { start, end },
+            },
+            Hook::OnlyBefore {
+                period: Period::Instant { value: end },
+            },
+            Hook::OnlyAfter {
+                period: Period::Duration { seconds: 60 },
+            },
+            Hook::OnlyFor {
+                duration_secs: 3600,
+            },
+        ]
+    );
+    assert_eq!(policy.timezone.as_deref(), Some("Europe/Berlin"));
+    assert_eq!(policy.max_attempts, Some(5));
+    let actions: Vec<_> = store
+        .get_audit_logs()
+        .iter()
+        .map(|a| a.action.as_str())
+        .collect();
+    assert_eq!(actions, ["init", "add", "add"]);
+
+    // The next save rewrites the store in the current format.
+    store.save().unwrap();
+    assert_eq!(header_version(&path), STORE_VERSION);
+    let reopened = SecretStore::open(&path, &passphrase).unwrap();
+    assert_eq!(reopened.get_policy("legacy"), Some(policy));
+    assert_eq!(
+        reopened.get_credential("db-cred").unwrap().policy_ids,
+        ["legacy"]
+    );
+}
+
+#[test]
+fn test_weekly_schedule() {
+    use chrono::{NaiveTime, Weekday};
+
+    let hm = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    let policy = Policy::new("business-hours")
+        .with_timezone("America/New_York")
+        .add_hook(Hook::OnlyDuring {
+            windows: vec![
+                Window::new(
+                    [
+                        Weekday::Mon,
+                        Weekday::Tue,
+                        Weekday::Wed,
+                        Weekday::Thu,
+                        Weekday::Fri,
+                    ],
+                    hm(9, 0),
+                    hm(12, 0),
+                ),
+                Window::new([Weekday::Mon, Weekday::Tue], hm(13, 0), hm(17, 0)),
+                // Friday night on-call, crossing midnight into Saturday.
+                Window::new([Weekday::Fri], hm(22, 0), hm(2, 0)),
+            ],
+        });
+
+    let at = |y, mo, d, h, mi| EvaluationContext {
+        now: Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap(),
+        ..Default::default()
+    };
+
+    // 2026-01-05 is a Monday; New York is UTC-5 in January.
+    assert_eq!(
+        policy.evaluate(&at(2026, 1, 5, 14, 0)).verdict,
+        Verdict::Accept
+    );
+    assert!(matches!(
+        policy.evaluate(&at(2026, 1, 5, 17, 30)).verdict, // 12:30 lunch gap
+        Verdict::PolicyViolation(_)
+    ));
+    assert_eq!(
+        policy.evaluate(&at(2026, 1, 5, 19, 0)).verdict,
+        Verdict::Accept
+    );
+    // Wednesday afternoon is outside the second window.
+    assert!(matches!(
+        policy.evaluate(&at(2026, 1, 7, 19, 0)).verdict,
+        Verdict::PolicyViolation(_)
+    ));
+    // Saturday 01:00 local belongs to Friday's overnight window.
+    assert_eq!(
+        policy.evaluate(&at(2026, 1, 10, 6, 0)).verdict,
+        Verdict::Accept
+    );
+    assert!(matches!(
+        policy.evaluate(&at(2026, 1, 10, 8, 0)).verdict,
+        Verdict::PolicyViolation(_)
+    ));
+    // In July New York is UTC-4, so 09:30 local is 13:30 UTC.
+    assert_eq!(
+        policy.evaluate(&at(2026, 7, 6, 13, 30)).verdict,
+        Verdict::Accept
+    );
+
+    let toml_policy = r#"
+        id = "business-hours"
+        timezone = "Europe/Berlin"
+        clock_skew_secs = 60
+        single_use = false
+        version = 1
+
+        [[hooks]]
+        type = "onlyDuring"
+
+        [[hooks.windows]]
+        days = ["Mon", "Tue", "Wed", "Thu", "Fri"]
+        start = "09:00"
+        end = "17:00"
+    "#;
+    let parsed: Policy = toml::from_str(toml_policy).unwrap();
+    let json = serde_json::to_string(&parsed).unwrap();
+    assert_eq!(serde_json::from_str::<Policy>(&json).unwrap(), parsed);
+}
+
+#[test]
+fn test_cron_window() {
+    // Maintenance window: two hours from 02:00 every Sunday, Berlin time.
+    let policy = Policy::new("maintenance")
+        .with_timezone("Europe/Berlin")
+        .add_hook(Hook::OnlyAt {
+            cron: "0 2 * * SUN".to_string(),
+            window_secs: 2 * 3600,
+        });
+
+    let at = |d, h, mi| EvaluationContext {
+        now: Utc.with_ymd_and_hms(2026, 1, d, h, mi, 0).unwrap(),
+        ..Default::default()
+    };
+
+    // 2026-01-04 is a Sunday; Berlin is UTC+1 in January. The window edges are
+    // widened by the default 60s clock skew.
+    assert!(matches!(
+        policy.evaluate(&at(4, 0, 58)).verdict,
+        Verdict::PolicyViolation(_)
+    ));
+    assert_eq!(policy.evaluate(&at(4, 1, 0)).verdict, Verdict::Accept);
+    assert_eq!(policy.evaluate(&at(4, 2, 59)).verdict, Verdict::Accept);
+    assert!(matches!(
+        policy.evaluate(&at(4, 3, 1)).verdict,
+        Verdict::PolicyViolation(_)
+    ));
+    assert!(matches!(
+        policy.evaluate(&at(5, 1, 30)).verdict,
+        Verdict::PolicyViolation(_)
+    ));
+
+    let malformed = Hook::OnlyAt {
+        cron: "0 2 * * FUNDAY".to_string(),
+        window_secs: 3600,
+    };
+    assert!(matches!(
+        malformed.validate(),
+        Err(timely_pass_sdk::Error::InvalidPeriod(_))
+    ));
+
+    let dir = tempdir().unwrap();
+    let mut store =
+        SecretStore::init(dir.path().join("store.timely"), &Secret::from("pw")).unwrap();
+    assert!(matches!(
+        store.add_policy(Policy::new("bad-cron").add_hook(malformed)),
+        Err(timely_pass_sdk::Error::InvalidPeriod(_))
+    ));
+}
+
+#[test]
+fn test_composite_hooks() {
+    use chrono::{NaiveTime, Weekday};
+
+    let utc = |y, mo, d| Utc.with_ymd_and_hms(y, mo, d, 0, 0, 0).unwrap();
+    let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+
+    // (within Q4 OR on weekends) AND NOT during the Dec 20-31 blackout.
+    let policy = Policy::new("composite")
+        .add_hook(Hook::any_of([
+            Hook::OnlyWithin {
+                period: Period::Range {
+                    start: utc(2026, 10, 1),
+                    end: utc(2027, 1, 1),
+                },
+            },
+            Hook::OnlyDuring {
+                windows: vec![Window::new(
+                    [Weekday::Sat, Weekday::Sun],
+                    midnight,
+                    midnight,
+                )],
+            },
+        ]))
+        .add_hook(Hook::not(Hook::OnlyWithin {
+            period: Period::Range {
+                start: utc(2026, 12, 20),
+                end: utc(2027, 1, 1),
+            },
+        }));
+
+    let at = |now| EvaluationContext {
+        now,
+        ..Default::default()
+    };
+
+    // Tuesday in Q4: first alternative satisfies the anyOf.
+    let eval = policy.evaluate(&at(Utc.with_ymd_and_hms(2026, 11, 3, 12, 0, 0).unwrap()));
+    assert_eq!(eval.verdict, Verdict::Accept);
+    assert_eq!(eval.matched_hooks, vec![0, 1]);
+    assert_eq!(eval.branches[0].path, "0");
+    assert_eq!(eval.branches[0].decided_by.as_deref(), Some("0.0"));
+
+    // Saturday in June: second alternative satisfies the anyOf.
+    let eval = policy.evaluate(&at(Utc.with_ymd_and_hms(2026, 6, 6, 12, 0, 0).unwrap()));
+    assert_eq!(eval.verdict, Verdict::Accept);
+    assert_eq!(eval.branches[0].decided_by.as_deref(), Some("0.1"));
+
+    // Tuesday in June: no alternative applies.
+    let eval = policy.evaluate(&at(Utc.with_ymd_and_hms(2026, 6, 2, 12, 0, 0).unwrap()));
+    assert!(matches!(eval.verdict, Verdict::PolicyViolation(_)));
+    assert_eq!(eval.details.get("failed_hook_path").unwrap(), "0");
+
+    // Inside the blackout the negated hook matches and rejects access.
+    let eval = policy.evaluate(&at(Utc.with_ymd_and_hms(2026, 12, 22, 12, 0, 0).unwrap()));
+    assert!(matches!(eval.verdict, Verdict::PolicyViolation(_)));
+    assert_eq!(eval.details.get("failed_hook_path").unwrap(), "1");
+    let not_branch = eval.branches.iter().find(|b| b.path == "1").unwrap();
+    assert!(!not_branch.satisfied);
+    assert_eq!(not_branch.decided_by.as_deref(), Some("1.0"));
+
+    // Composites round-trip through JSON.
+    let json = serde_json::to_string(&policy).unwrap();
+    assert_eq!(serde_json::from_str::<Policy>(&json).unwrap(), policy);
+}
+
+#[test]
+fn test_clock_skew_tolerance() {
+    let boundary = Utc.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+    let mut policy = Policy::new("skewed")
+        .add_hook(Hook::OnlyBefore {
+            period: Period::Instant { value: boundary },
+        })
+        .add_hook(Hook::OnlyFor {
+            duration_secs: 3600,
+        });
+    policy.clock_skew_secs = 120;
+
+    let ctx = |now| EvaluationContext {
+        now,
+        created_at: Some(boundary - Duration::hours(1)),
+        ..Default::default()
+    };
+
+    // Well inside every boundary: accepted without needing the allowance.
+    let eval = policy.evaluate(&ctx(boundary - Duration::minutes(10)));
+    assert_eq!(eval.verdict, Verdict::Accept);
+    assert!(!eval.details.contains_key("clock_skew_applied_secs"));
+
+    // 90s past both boundaries: accepted, but only thanks to the skew.
+    let eval = policy.evaluate(&ctx(boundary + Duration::seconds(90)));
+    assert_eq!(eval.verdict, Verdict::Accept);
+    assert_eq!(eval.details.get("clock_skew_applied_secs").unwrap(), "120");
+
+    // Beyond the tolerance the credential is expired.
+    let eval = policy.evaluate(&ctx(boundary + Duration::seconds(121)));
+    assert_eq!(eval.verdict, Verdict::Expired);
+
+    // OnlyAfter and OnlyWithin are widened on the other side as well.
+    let policy = Policy::new("window").add_hook(Hook::OnlyWithin {
+        period: Period::Range {
+            start: boundary,
+            end: boundary + Duration::hours(1),
+        },
+    });
+    let early = policy.evaluate(&ctx(boundary - Duration::seconds(30)));
+    assert_eq!(early.verdict, Verdict::Accept);
+    assert_eq!(early.details.get("clock_skew_applied_secs").unwrap(), "60");
+    assert!(matches!(
+        policy
+            .evaluate(&ctx(boundary - Duration::seconds(61)))
+            .verdict,
+        Verdict::PolicyViolation(_)
+    ));
+
+    // Durations too long to add to a date are refused by validation, and are
+    // treated as never running out instead of overflowing.
+    let mut huge = Policy::new("huge").add_hook(Hook::OnlyFor {
+        duration_secs: u64::MAX,
+    });
+    huge.clock_skew_secs = 9_223_372_036_854_775;
+    let paths: Vec<String> = huge.validate().into_iter().map(|d| d.path).collect();
+    assert_eq!(paths, ["clock_skew_secs", "hooks[0].duration_secs"]);
+    assert_eq!(huge.evaluate(&ctx(boundary)).verdict, Verdict::Accept);
+    assert_eq!(huge.explain(&ctx(boundary)).verdict, Verdict::Accept);
+    assert!(huge.next_transition(&ctx(boundary)).is_none());
+}
+
+#[test]
+fn test_disabled_policy() {
+    let dir = tempdir().unwrap();
+    let mut store =
+        SecretStore::init(dir.path().join("store.timely"), &Secret::from("pw")).unwrap();
+
+    let mut policy = Policy::new("kill-switch");
+    policy.enabled = false;
+    store.add_policy(policy.clone()).unwrap();
+
+    let mut cred = Credential::new("svc".to_string(), SecretType::Token, b"t".to_vec());
+    cred.policy_ids = vec![policy.id.clone()];
+    let cred_id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    // Disabled policies deny by default and leave an audit trail.
+    let eval = store
+        .evaluate_credential(&cred_id, Utc::now())
+        .unwrap()
+        .unwrap();
+    assert_eq!(eval.verdict, Verdict::Disabled);
+    let last = store.get_audit_logs().last().unwrap();
+    assert_eq!(last.action, "deny");
+    assert_eq!(last.target_id, cred_id);
+    assert!(last.details.contains("disabled"));
+
+    // In bypass mode the policy is skipped, even if its hooks would deny.
+    policy.when_disabled = DisabledMode::Bypass;
+    policy.hooks.push(Hook::OnlyBefore {
+        period: Period::Instant {
+            value: Utc::now() - Duration::days(1),
+        },
+    });
+    store.add_policy(policy).unwrap();
+    let eval = store
+        .evaluate_credential(&cred_id, Utc::now())
+        .unwrap()
+        .unwrap();
+    assert_eq!(eval.verdict, Verdict::Accept);
+    assert_eq!(eval.details.get("policy_disabled").unwrap(), "bypassed");
+}
+
+#[test]
+fn test_idle_timeout_tracks_last_use() {
+    let dir = tempdir().unwrap();
+    let mut store =
+        SecretStore::init(dir.path().join("store.timely"), &Secret::from("pw")).unwrap();
+
+    let policy = Policy::new("idle").add_hook(Hook::IdleTimeout { idle_secs: 3600 });
+    store.add_policy(policy.clone()).unwrap();
+
+    let mut cred = Credential::new("svc".to_string(), SecretType::Token, b"t".to_vec());
+    cred.created_at = Utc::now() - Duration::days(1);
+    cred.policy_ids = vec![policy.id.clone()];
+    let cred_id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    // Never used and created a day ago: idle since creation.
+    let eval = store
+        .evaluate_credential(&cred_id, Utc::now())
+        .unwrap()
+        .unwrap();
+    assert_eq!(eval.verdict, Verdict::Expired);
+
+    store.increment_usage(&cred_id).unwrap();
+    let last_used = store.get_credential(&cred_id).unwrap().last_used_at;
+    assert!(last_used.is_some());
+
+    let check = |store: &mut SecretStore, offset| {
+        store
+            .evaluate_credential(&cred_id, last_used.unwrap() + offset)
+            .unwrap()
+            .unwrap()
+            .verdict
+    };
+    assert_eq!(check(&mut store, Duration::minutes(30)), Verdict::Accept);
+    assert_eq!(check(&mut store, Duration::hours(2)), Verdict::Expired);
+}
+
+#[test]
+fn test_rate_limit() {
+    let dir = tempdir().unwrap();
+    let mut store =
+        SecretStore::init(dir.path().join("store.timely"), &Secret::from("pw")).unwrap();
+
+    let policy = Policy::new("hourly").add_hook(Hook::RateLimit {
+        max_uses: 2,
+        window_secs: 3600,
+    });
+    store.add_policy(policy.clone()).unwrap();
+
+    let mut cred = Credential::new("api".to_string(), SecretType::Key, b"k".to_vec());
+    cred.policy_ids = vec![policy.id.clone()];
+    // Stale history from yesterday is trimmed on the next access.
+    cred.access_history = vec![Utc::now() - Duration::days(1); 5];
+    let cred_id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    store.increment_usage(&cred_id).unwrap();
+    assert_eq!(
+        store.get_credential(&cred_id).unwrap().access_history.len(),
+        1
+    );
+    store.increment_usage(&cred_id).unwrap();
+
+    let history = store
+        .get_credential(&cred_id)
+        .unwrap()
+        .access_history
+        .clone();
+    let now = history[1] + Duration::seconds(1);
+    let eval = store.evaluate_credential(&cred_id, now).unwrap().unwrap();
+    assert_eq!(
+        eval.verdict,
+        Verdict::RateLimited {
+            retry_after: history[0] + Duration::hours(1)
+        }
+    );
+
+    // Once the oldest use leaves the window, access is allowed again.
+    let later = history[0] + Duration::hours(1) + Duration::seconds(1);
+    let eval = store.evaluate_credential(&cred_id, later).unwrap().unwrap();
+    assert_eq!(eval.verdict, Verdict::Accept);
+}
+
+#[test]
+fn test_policy_validation() {
+    use timely_pass_sdk::policy::{DiagnosticKind, Severity};
+
+    let t = |d| Utc.with_ymd_and_hms(2026, 1, d, 0, 0, 0).unwrap();
+
+    // A well-formed policy has no diagnostics.
+    let good = Policy::new("good")
+        .add_hook(Hook::OnlyAfter {
+            period: Period::Instant { value: t(1) },
+        })
+        .add_hook(Hook::OnlyBefore {
+            period: Period::Instant { value: t(31) },
+        });
+    assert!(good.validate().is_empty());
+
+    let mut bad = Policy::new("bad")
+        .with_timezone("Atlantis/Capital")
+        .add_hook(Hook::OnlyBefore {
+            period: Period::Range {
+                start: t(1),
+                end: t(2),
+            },
+        })
+        .add_hook(Hook::any_of([Hook::OnlyWithin {
+            period: Period::Range {
+                start: t(10),
+                end: t(5),
+            },
+        }]))
+        .add_hook(Hook::OnlyAfter {
+            period: Period::Instant { value: t(20) },
+        })
+        .add_hook(Hook::OnlyWithin {
+            period: Period::Range {
+                start: t(1),
+                end: t(15),
+            },
+        });
+    bad.max_attempts = Some(0);
+
+    let diagnostics = bad.validate();
+    let find = |kind| {
+        diagnostics
+            .iter()
+            .find(|d| d.kind == kind)
+            .unwrap_or_else(|| panic!("missing {:?} in {:?}", kind, diagnostics))
+    };
+    assert_eq!(find(DiagnosticKind::UnknownTimezone).path, "timezone");
+    assert_eq!(
+        find(DiagnosticKind::InvalidPeriodKind).path,
+        "hooks[0].period"
+    );
+    assert_eq!(
+        find(DiagnosticKind::EmptyRange).path,
+        "hooks[1].hooks[0].period"
+    );
+    // onlyAfter Jan 20 contradicts onlyWithin Jan 1-15.
+    let contradiction = diagnostics
+        .iter()
+        .find(|d| d.kind == DiagnosticKind::Unsatisfiable && d.path == "hooks[3]")
+        .unwrap();
+    assert!(contradiction.message.contains("hooks[2]"));
+    assert!(diagnostics
+        .iter()
+        .any(|d| d.path == "max_attempts" && d.severity == Severity::Error));
+
+    // Contradictions are judged after widening by the clock skew, as evaluation is.
+    let edge = |secs| Period::Instant {
+        value: t(2) + Duration::seconds(secs),
+    };
+    let mut overlapping = Policy::new("overlapping")
+        .add_hook(Hook::OnlyAfter { period: edge(30) })
+        .add_hook(Hook::OnlyBefore { period: edge(0) });
+    assert!(overlapping.validate().is_empty());
+    let ctx = EvaluationContext {
+        now: t(2) + Duration::seconds(10),
+        ..Default::default()
+    };
+    assert_eq!(overlapping.evaluate(&ctx).verdict, Verdict::Accept);
+    overlapping.clock_skew_secs = 0;
+    assert_eq!(
+        overlapping.validate()[0].kind,
+        DiagnosticKind::Unsatisfiable
+    );
+
+    // The store refuses policies with errors.
+    let dir = tempdir().unwrap();
+    let mut store =
+        SecretStore::init(dir.path().join("store.timely"), &Secret::from("pw")).unwrap();
+    let mut unsatisfiable = good.clone();
+    unsatisfiable.clock_skew_secs = 0;
+    unsatisfiable.hooks.push(Hook::OnlyAfter {
+        period: Period::Instant { value: t(31) },
+    });
+    assert!(matches!(
+        store.add_policy(unsatisfiable),
+        Err(timely_pass_sdk::Error::InvalidPolicy(_))
+    ));
+    store.add_policy(good).unwrap();
+}
+
+#[test]
+fn test_explain_traces_every_hook() {
+    let t = |day| Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap();
+    let mut policy = Policy::new("explain")
+        .add_hook(Hook::OnlyBefore {
+            period: Period::Instant { value: t(10) },
+        })
+        .add_hook(Hook::OnlyAfter {
+            period: Period::Instant { value: t(1) },
+        })
+        .add_hook(Hook::any_of([
+            Hook::OnlyFor { duration_secs: 60 },
+            Hook::not(Hook::OnlyAfter {
+                period: Period::Instant { value: t(20) },
+            }),
+        ]));
+    policy.clock_skew_secs = 30;
+
+    let ctx = EvaluationContext {
+        now: t(15),
+        created_at: Some(t(1)),
+        ..Default::default()
+    };
+
+    // evaluate stops at the first failure; explain keeps going.
+    let short = policy.evaluate(&ctx);
+    assert!(short.trace.is_empty());
+    assert!(short.matched_hooks.is_empty());
+
+    let full = policy.explain(&ctx);
+    assert_eq!(full.verdict, Verdict::Expired);
+    assert_eq!(full.matched_hooks, vec![1, 2]);
+
+    let paths: Vec<_> = full.trace.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, ["0", "1", "2", "2.0", "2.1", "2.1.0"]);
+
+    let before = &full.trace[0];
+    assert_eq!(before.kind, "onlyBefore");
+    assert!(!before.passed);
+    assert_eq!(
+        before.reason.as_deref(),
+        Some("Expired (After allowed time)")
+    );
+    assert_eq!(before.skew_secs, 30);
+    assert_eq!(before.boundaries[0].at, t(10));
+    assert_eq!(
+        before.boundaries[0].effective,
+        t(10) + Duration::seconds(30)
+    );
+
+    assert!(!full.trace[3].passed); // onlyFor elapsed
+    assert!(full.trace[4].passed); // not(onlyAfter Jan 20)
+    assert!(!full.trace[5].passed);
+}
+
+#[test]
+fn test_next_transition() {
+    let at = |day, h, m| Utc.with_ymd_and_hms(2025, 1, day, h, m, 0).unwrap();
+    let ctx_at = |now| EvaluationContext {
+        now,
+        created_at: Some(at(1, 0, 0)),
+        ..Default::default()
+    };
+
+    let mut fixed = Policy::new("fixed")
+        .add_hook(Hook::OnlyAfter {
+            period: Period::Instant { value: at(2, 0, 0) },
+        })
+        .add_hook(Hook::OnlyBefore {
+            period: Period::Instant {
+                value: at(10, 0, 0),
+            },
+        });
+    fixed.clock_skew_secs = 0;
+
+    // onlyAfter is exclusive, so access opens one second after the boundary.
+    let opens = fixed.next_transition(&ctx_at(at(1, 0, 0))).unwrap();
+    assert_eq!(opens.at, at(2, 0, 0) + Duration::seconds(1));
+    assert_eq!(opens.verdict, Verdict::Accept);
+
+    let expires = fixed.next_transition(&ctx_at(at(5, 0, 0))).unwrap();
+    assert_eq!(expires.at, at(10, 0, 0));
+    assert_eq!(expires.verdict, Verdict::Expired);
+    assert!(fixed.next_transition(&ctx_at(at(11, 0, 0))).is_none());
+
+    // Weekday office hours that only start counting from Saturday 11 Jan: the
+    // next opening is Monday 13 Jan at 09:00, not one of the windows before it.
+    let weekdays = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ];
+    let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+    let five = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+    let mut office = Policy::new("office")
+        .add_hook(Hook::OnlyDuring {
+            windows: vec![Window::new(weekdays, nine, five)],
+        })
+        .add_hook(Hook::OnlyAfter {
+            period: Period::Instant {
+                value: at(11, 0, 0),
+            },
+        });
+    office.clock_skew_secs = 0;
+    let next = office.next_transition(&ctx_at(at(6, 10, 0))).unwrap();
+    assert_eq!(next.at, at(13, 9, 0));
+    assert_eq!(next.verdict, Verdict::Accept);
+
+    let closes = office.next_transition(&ctx_at(at(13, 12, 0))).unwrap();
+    assert_eq!(closes.at, at(13, 17, 0));
+    assert!(matches!(closes.verdict, Verdict::PolicyViolation(_)));
+
+    // Cron windows, widened by the clock skew.
+    let daily = Policy::new("daily").add_hook(Hook::OnlyAt {
+        cron: "0 9 * * *".to_string(),
+        window_secs: 3600,
+    });
+    let skew = Duration::seconds(daily.clock_skew_secs as i64);
+    assert_eq!(
+        daily.next_transition(&ctx_at(at(6, 8, 0))).unwrap().at,
+        at(6, 9, 0) - skew
+    );
+    assert_eq!(
+        daily.next_transition(&ctx_at(at(6, 9, 30))).unwrap().at,
+        at(6, 10, 0) + skew
+    );
+
+    // A disabled policy never changes.
+    let mut disabled = fixed.clone();
+    disabled.enabled = false;
+    assert!(disabled.next_transition(&ctx_at(at(1, 0, 0))).is_none());
+}
+
+#[test]
+fn test_simulate_intervals() {
+    let at = |day, h, m| Utc.with_ymd_and_hms(2025, 1, day, h, m, 0).unwrap();
+    let weekdays = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ];
+    let mut policy = Policy::new("office")
+        .add_hook(Hook::OnlyDuring {
+            windows: vec![Window::new(
+                weekdays,
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            )],
+        })
+        .add_hook(Hook::OnlyBefore {
+            period: Period::Instant {
+                value: at(7, 12, 0),
+            },
+        });
+    policy.clock_skew_secs = 0;
+
+    // Monday 6 Jan through Wednesday 8 Jan.
+    let ctx = EvaluationContext {
+        now: at(6, 0, 0),
+        created_at: Some(at(6, 0, 0)),
+        ..Default::default()
+    };
+    let intervals = policy.simulate(&ctx, at(8, 0, 0));
+    let summary: Vec<_> = intervals
+        .iter()
+        .map(|i| (i.start, i.end, i.verdict.clone()))
+        .collect();
+    let outside = Verdict::PolicyViolation("Outside scheduled hours".to_string());
+    assert_eq!(
+        summary,
+        vec![
+            (at(6, 0, 0), at(6, 9, 0), outside.clone()),
+            (at(6, 9, 0), at(6, 17, 0), Verdict::Accept),
+            (at(6, 17, 0), at(7, 9, 0), outside.clone()),
+            (at(7, 9, 0), at(7, 12, 0), Verdict::Accept),
+            (at(7, 12, 0), at(7, 17, 0), Verdict::Expired),
+            // Hooks are checked in order, so the schedule reports first once it closes.
+            (at(7, 17, 0), at(8, 0, 0), outside),
+        ]
+    );
+
+    // Consecutive intervals cover the range without gaps.
+    assert!(intervals.windows(2).all(|w| w[0].end == w[1].start));
+    assert!(intervals.iter().all(|i| !i.truncated));
+    assert!(policy.simulate(&ctx, ctx.now).is_empty());
+
+    // Back-to-back windows never change the verdict, but every edge costs a
+    // search step; over two centuries the search gives up and says so.
+    let every_day = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ];
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+    let mut always = Policy::new("always").add_hook(Hook::OnlyDuring {
+        windows: vec![
+            Window::new(every_day, NaiveTime::MIN, noon),
+            Window::new(every_day, noon, NaiveTime::MIN),
+        ],
+    });
+    always.clock_skew_secs = 0;
+    let until = Utc.with_ymd_and_hms(2225, 1, 1, 0, 0, 0).unwrap();
+    let intervals = always.simulate(&ctx, until);
+    assert_eq!(intervals.len(), 1);
+    assert_eq!(intervals[0].verdict, Verdict::Accept);
+    assert!(intervals[0].truncated && intervals[0].end < until);
+}
+
+#[test]
+fn test_blackout_calendar() {
+    let date = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let pw = Secret::from("pw");
+    let mut store = SecretStore::init_with_clock(&path, &pw, clock_before_2025()).unwrap();
+
+    let policy = Policy::new("no-holidays")
+        .with_timezone("Europe/Berlin")
+        .add_hook(Hook::Blackout {
+            calendar: "holidays".to_string(),
+        });
+
+    // Policies may only reference calendars that exist.
+    assert!(matches!(
+        store.add_policy(policy.clone()),
+        Err(timely_pass_sdk::Error::InvalidPolicy(_))
+    ));
+
+    let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20251225\r\nDTEND;VALUE=DATE:20251227\r\nSUMMARY:Christmas\\, Boxing Day\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART:20251231T090000Z\r\nSUMMARY:New Year's\r\n  Eve\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    let holidays = Calendar::from_ics("holidays", ics)
+        .unwrap()
+        .add_entry(CalendarEntry::range(date(8, 1), date(8, 3)).with_label("Freeze"));
+    assert_eq!(holidays.entries[0].start, date(12, 25));
+    assert_eq!(holidays.entries[0].end, date(12, 26));
+    assert_eq!(
+        holidays.entries[0].label.as_deref(),
+        Some("Christmas, Boxing Day")
+    );
+    assert_eq!(holidays.entries[1].end, date(12, 31));
+    assert_eq!(holidays.entries[1].label.as_deref(), Some("New Year's Eve"));
+    assert!(Calendar::from_ics(
+        "x",
+        "BEGIN:VEVENT\nDTSTART:20250101\nRRULE:FREQ=YEARLY\nEND:VEVENT\n"
+    )
+    .is_err());
+
+    store.add_calendar(holidays).unwrap();
+    store.add_policy(policy).unwrap();
+    let mut cred = Credential::new("vpn".to_string(), SecretType::Password, b"x".to_vec());
+    cred.policy_ids = vec!["no-holidays".to_string()];
+    let id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    // Calendars are persisted with the store.
+    let mut store = SecretStore::open_with_clock(&path, &pw, clock_before_2025()).unwrap();
+    assert_eq!(store.list_calendars().len(), 1);
+
+    // Berlin is UTC+1 in winter: 23:30 UTC on 24 Dec is already Christmas Day.
+    let at = |m, d, h, min| Utc.with_ymd_and_hms(2025, m, d, h, min, 0).unwrap();
+    let verdict = |store: &mut SecretStore, now| {
+        store
+            .evaluate_credential(&id, now)
+            .unwrap()
+            .unwrap()
+            .verdict
+    };
+    assert_eq!(verdict(&mut store, at(12, 24, 12, 0)), Verdict::Accept);
+    assert!(matches!(
+        verdict(&mut store, at(12, 24, 23, 30)),
+        Verdict::PolicyViolation(_)
+    ));
+    assert!(matches!(
+        verdict(&mut store, at(8, 2, 12, 0)),
+        Verdict::PolicyViolation(_)
+    ));
+    // The blackout ends at local midnight after 26 Dec, give or take the skew.
+    let transition = store
+        .next_transition(&id, at(12, 25, 12, 0))
+        .unwrap()
+        .unwrap();
+    assert_eq!(transition.at, at(12, 26, 23, 0) - Duration::seconds(60));
+    assert_eq!(transition.verdict, Verdict::Accept);
+
+    // A calendar in use cannot be removed.
+    assert!(store.remove_calendar("holidays").is_err());
+    store.remove_credential(&id).unwrap();
+    store.remove_policy("no-holidays").unwrap();
+    store.remove_calendar("holidays").unwrap();
+}
+
+#[test]
+fn test_policy_inheritance() {
+    let at = |day| Utc.with_ymd_and_hms(2025, 1, day, 12, 0, 0).unwrap();
+    let dir = tempdir().unwrap();
+    let mut store = SecretStore::init_with_clock(
+        dir.path().join("store.timely"),
+        &Secret::from("pw"),
+        clock_before_2025(),
+    )
+    .unwrap();
+
+    let mut base = Policy::new("base").add_hook(Hook::OnlyBefore {
+        period: Period::Instant { value: at(20) },
+    });
+    base.max_attempts = Some(10);
+    store.add_policy(base.clone()).unwrap();
+
+    let mut derived = Policy::new("derived").add_hook(Hook::OnlyAfter {
+        period: Period::Instant { value: at(5) },
+    });
+    derived.extends = Some(Extends {
+        policy: "base".to_string(),
+        hooks: HookMerge::Append,
+        clock_skew_secs: Some(0),
+        max_attempts: None,
+    });
+    store.add_policy(derived.clone()).unwrap();
+
+    let resolved = store.resolve_policy("derived").unwrap().unwrap();
+    assert_eq!(resolved.hooks.len(), 2);
+    assert_eq!(resolved.hooks[0], base.hooks[0]);
+    assert_eq!(resolved.clock_skew_secs, 0);
+    assert_eq!(resolved.max_attempts, Some(10));
+    assert!(resolved.extends.is_none());
+
+    let mut cred = Credential::new("c".to_string(), SecretType::Token, b"t".to_vec());
+    cred.policy_ids = vec!["derived".to_string()];
+    let id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+    let verdict = |store: &mut SecretStore, day| {
+        store
+            .evaluate_credential(&id, at(day))
+            .unwrap()
+            .unwrap()
+            .verdict
+    };
+    assert_eq!(verdict(&mut store, 10), Verdict::Accept);
+    assert_eq!(verdict(&mut store, 25), Verdict::Expired);
+
+    // Changing the base applies to every derived policy.
+    base.hooks = vec![Hook::OnlyBefore {
+        period: Period::Instant { value: at(8) },
+    }];
+    store.add_policy(base.clone()).unwrap();
+    assert_eq!(verdict(&mut store, 10), Verdict::Expired);
+
+    // A base change that makes a derived policy unsatisfiable is refused.
+    let mut contradictory = base.clone();
+    contradictory.hooks = vec![Hook::OnlyBefore {
+        period: Period::Instant { value: at(2) },
+    }];
+    assert!(matches!(
+        store.add_policy(contradictory),
+        Err(timely_pass_sdk::Error::InvalidPolicy(_))
+    ));
+
+    // Replace drops the base hooks.
+    let mut replaced = derived.clone();
+    replaced.extends.as_mut().unwrap().hooks = HookMerge::Replace;
+    store.add_policy(replaced).unwrap();
+    assert_eq!(verdict(&mut store, 10), Verdict::Accept);
+
+    // Disabling the base disables every derived policy.
+    base.enabled = false;
+    store.add_policy(base.clone()).unwrap();
+    assert_eq!(verdict(&mut store, 10), Verdict::Disabled);
+    base.enabled = true;
+    store.add_policy(base.clone()).unwrap();
+
+    // Cycles and unknown bases are rejected; bases in use cannot be removed.
+    let mut cyclic = base.clone();
+    cyclic.extends = Some(Extends {
+        policy: "derived".to_string(),
+        hooks: HookMerge::Append,
+        clock_skew_secs: None,
+        max_attempts: None,
+    });
+    let err = store.add_policy(cyclic).unwrap_err().to_string();
+    assert!(err.contains("cycle"), "{}", err);
+
+    let mut orphan = derived.clone();
+    orphan.id = "orphan".to_string();
+    orphan.extends.as_mut().unwrap().policy = "missing".to_string();
+    assert!(store.add_policy(orphan).is_err());
+    assert!(store.remove_policy("base").is_err());
+}
+
+#[test]
+fn test_inherited_hooks_keep_base_timezone() {
+    let dir = tempdir().unwrap();
+    let mut store =
+        SecretStore::init(dir.path().join("store.timely"), &Secret::from("pw")).unwrap();
+
+    let office = Window::new(
+        [
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ],
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+    );
+    let base = Policy::new("berlin-office")
+        .with_timezone("Europe/Berlin")
+        .add_hook(Hook::OnlyDuring {
+            windows: vec![office],
+        });
+    store.add_policy(base).unwrap();
+
+    let mut derived = Policy::new("contractor");
+    derived.extends = Some(Extends {
+        policy: "berlin-office".to_string(),
+        hooks: HookMerge::Append,
+        clock_skew_secs: Some(0),
+        max_attempts: None,
+    });
+    store.add_policy(derived.clone()).unwrap();
+
+    // A derived policy without a zone of its own evaluates in the base's zone:
+    // 16:30 UTC on a Monday is 17:30 in Berlin, after the window closes.
+    let resolved = store.resolve_policy("contractor").unwrap().unwrap();
+    assert_eq!(resolved.timezone.as_deref(), Some("Europe/Berlin"));
+    let verdict = |hour| {
+        let ctx = EvaluationContext {
+            now: Utc.with_ymd_and_hms(2025, 1, 6, hour, 30, 0).unwrap(),
+            ..Default::default()
+        };
+        resolved.evaluate(&ctx).verdict
+    };
+    assert_eq!(verdict(8), Verdict::Accept);
+    assert!(matches!(verdict(16), Verdict::PolicyViolation(_)));
+
+    // Appending the base hooks under another zone would shift them, so it is
+    // refused; replacing them is fine.
+    let elsewhere = derived.clone().with_timezone("America/New_York");
+    let err = store.add_policy(elsewhere.clone()).unwrap_err().to_string();
+    assert!(err.contains("America/New_York"), "{}", err);
+    let mut replaced = elsewhere;
+    replaced.extends.as_mut().unwrap().hooks = HookMerge::Replace;
+    store.add_policy(replaced).unwrap();
+    store
+        .add_policy(derived.with_timezone("Europe/Berlin"))
+        .unwrap();
+}
+
+#[test]
+fn test_policy_history_and_rollback() {
+    let at = |day| Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap();
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let pw = Secret::from("pw");
+    let mut store = SecretStore::init(&path, &pw).unwrap();
+
+    let v1 = Policy::new("p").add_hook(Hook::OnlyBefore {
+        period: Period::Instant { value: at(10) },
+    });
+    store.add_policy(v1.clone()).unwrap();
+
+    // Re-adding with the same version number still records a new version.
+    let mut v2 = v1.clone();
+    v2.clock_skew_secs = 0;
+    v2.hooks.push(Hook::OnlyAfter {
+        period: Period::Instant { value: at(1) },
+    });
+    store.add_policy(v2).unwrap();
+    assert_eq!(store.get_policy("p").unwrap().version, 2);
+
+    let changes = store
+        .get_policy_version("p", 1)
+        .unwrap()
+        .diff(store.get_policy("p").unwrap());
+    let paths: Vec<_> = changes.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(paths, ["clock_skew_secs", "hooks[1]", "version"]);
+    assert_eq!(changes[0].before, Some(serde_json::json!(60)));
+    assert_eq!(changes[0].after, Some(serde_json::json!(0)));
+    assert!(changes[1].before.is_none());
+
+    assert_eq!(store.rollback_policy("p", 1).unwrap(), 3);
+    let current = store.get_policy("p").unwrap();
+    assert_eq!(current.hooks, v1.hooks);
+    assert_eq!(current.version, 3);
+    assert!(store.rollback_policy("p", 9).is_err());
+
+    // History survives reopening and records the audit entry behind each version.
+    let store = SecretStore::open(&path, &pw).unwrap();
+    let history = store.policy_history("p");
+    let versions: Vec<_> = history.iter().map(|r| r.version).collect();
+    assert_eq!(versions, [1, 2, 3]);
+    assert_eq!(history[2].audit.action, "rollback");
+    assert!(history[2].audit.details.contains("version 1"));
+}
+
+#[test]
+fn test_policy_dsl() {
+    let policy = dsl::parse_policy(
+        "within 2026-01-01..2026-12-31 and weekdays 09:00-17:00 Europe/Berlin and max 3 uses",
+    )
+    .unwrap();
+    let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+    let five = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+    let day = |m, d| NaiveDate::from_ymd_opt(2026, m, d).unwrap();
+    assert_eq!(
+        policy.hooks,
+        [
+            Hook::OnlyWithin {
+                period: Period::LocalRange {
+                    start: day(1, 1).and_time(NaiveTime::MIN),
+                    end: day(12, 31).and_hms_opt(23, 59, 59).unwrap(),
+                },
+            },
+            Hook::OnlyDuring {
+                windows: vec![Window::new(
+                    [
+                        Weekday::Mon,
+                        Weekday::Tue,
+                        Weekday::Wed,
+                        Weekday::Thu,
+                        Weekday::Fri
+                    ],
+                    nine,
+                    five,
+                )],
+            },
+        ]
+    );
+    assert_eq!(policy.timezone.as_deref(), Some("Europe/Berlin"));
+    assert_eq!(policy.max_attempts, Some(3));
+
+    // Every field and hook kind survives printing and parsing again.
+    let mut full = Policy::new("ops rotation")
+        .add_hook(Hook::any_of([
+            Hook::all_of([
+                Hook::OnlyAfter {
+                    period: Period::Instant {
+                        value: Utc.with_ymd_and_hms(2026, 3, 1, 8, 30, 0).unwrap(),
+                    },
+                },
+                Hook::not(Hook::Blackout {
+                    calendar: "and".to_string(),
+                }),
+            ]),
+            Hook::OnlyAt {
+                cron: "0 2 * * SUN".to_string(),
+                window_secs: 7_200,
+            },
+        ]))
+        .add_hook(Hook::OnlyDuring {
+            windows: vec![
+                Window::new([Weekday::Mon, Weekday::Wed], five, nine),
+                Window::new([Weekday::Sat], nine, five),
+            ],
+        })
+        .add_hook(Hook::OnlyBefore {
+            period: Period::LocalInstant {
+                value: day(6, 1).and_hms_opt(12, 15, 0).unwrap(),
+            },
+        })
+        .add_hook(Hook::RateLimit {
+            max_uses: 5,
+            window_secs: 5_400,
+        })
+        .add_hook(Hook::all_of([Hook::IdleTimeout { idle_secs: 0 }]))
+        .add_hook(Hook::any_of([]))
+        .with_timezone("America/New_York");
+    full.clock_skew_secs = 0;
+    full.single_use = true;
+    full.enabled = false;
+    full.when_disabled = DisabledMode::Bypass;
+    full.version = 4;
+    full.lockout = Some(Lockout {
+        max_failures: 3,
+        cooldown_secs: 60,
+        backoff: Backoff::Exponential { max_secs: 3_600 },
+    });
+    full.extends = Some(Extends {
+        policy: "base".to_string(),
+        hooks: HookMerge::Replace,
+        clock_skew_secs: Some(30),
+        max_attempts: None,
+    });
+    let text = dsl::format_policy(&full);
+    assert!(text.contains("max 5 uses per 1h30m"), "{}", text);
+    assert_eq!(dsl::parse_policy(&text).unwrap(), full);
+
+    let err = dsl::parse_policy("before 2026-01-01 and after 2025-01-01 or for 1d").unwrap_err();
+    assert!(err.to_string().contains("line 1, column 40"), "{}", err);
+}
+
+#[test]
+fn test_typed_reasons() {
+    let now = Utc.with_ymd_and_hms(2025, 12, 25, 12, 0, 0).unwrap();
+    let deadline = Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap();
+    let ctx = EvaluationContext {
+        now,
+        usage_count: 3,
+        access_history: vec![now - Duration::minutes(30), now - Duration::minutes(10)],
+        calendars: [(
+            "holidays".to_string(),
+            Calendar::new("holidays").add_entry(
+                CalendarEntry::day(NaiveDate::from_ymd_opt(2025, 12, 25).unwrap())
+                    .with_label("Christmas"),
+            ),
+        )]
+        .into(),
+        ..Default::default()
+    };
+
+    let expired = Policy::new("p").add_hook(Hook::OnlyBefore {
+        period: Period::Instant { value: deadline },
+    });
+    let eval = expired.evaluate(&ctx);
+    assert_eq!(eval.verdict, Verdict::Expired);
+    assert_eq!(eval.reason, Some(Reason::Expired { at: Some(deadline) }));
+    // The string details are still filled for existing callers.
+    assert_eq!(
+        eval.details.get("reason").unwrap(),
+        &eval.reason.unwrap().to_string()
+    );
+
+    let limited = Policy::new("p").add_hook(Hook::RateLimit {
+        max_uses: 2,
+        window_secs: 3600,
+    });
+    assert_eq!(
+        limited.evaluate(&ctx).reason,
+        Some(Reason::RateLimited {
+            max_uses: 2,
+            window_secs: 3600,
+            retry_after: now + Duration::minutes(30),
+        })
+    );
+
+    let blackout = Policy::new("p").add_hook(Hook::Blackout {
+        calendar: "holidays".to_string(),
+    });
+    match blackout.evaluate(&ctx).reason {
+        Some(Reason::BlackoutDate { date, label, .. }) => {
+            assert_eq!(date, NaiveDate::from_ymd_opt(2025, 12, 25).unwrap());
+            assert_eq!(label.as_deref(), Some("Christmas"));
+        }
+        other => panic!("unexpected reason {:?}", other),
+    }
+
+    let mut capped = Policy::new("p");
+    capped.max_attempts = Some(3);
+    assert_eq!(
+        capped.evaluate(&ctx).reason,
+        Some(Reason::MaxAttemptsExceeded {
+            max_attempts: 3,
+            used: 3,
+        })
+    );
+
+    let negated = Policy::new("p").add_hook(Hook::not(Hook::OnlyAfter {
+        period: Period::Instant { value: deadline },
+    }));
+    let eval = negated.evaluate(&ctx);
+    assert_eq!(eval.reason, Some(Reason::ExcludedByNegation));
+    assert_eq!(
+        eval.verdict,
+        Verdict::PolicyViolation("Excluded by negated hook".to_string())
+    );
+
+    // Reasons serialize with a type tag, so they can be matched outside Rust too.
+    let json = serde_json::to_value(Reason::SingleUseConsumed).unwrap();
+    assert_eq!(json, serde_json::json!({ "type": "singleUseConsumed" }));
+    assert!(Policy::new("p").evaluate(&ctx).reason.is_none());
+}
+
+#[test]
+fn test_strict_policy_files() {
+    let json = r#"{
+        "id": "p",
+        "hooks": [{ "type": "onlyFor", "duration_secs": 60 }],
+        "clock_skew_secs": 60,
+        "max_attempts": null,
+        "single_use": false,
+        "version": 1
+    }"#;
+    let policy = Policy::from_json(json).unwrap();
+    assert_eq!(policy.hooks, [Hook::OnlyFor { duration_secs: 60 }]);
+
+    // Typos are reported with the path of the offending key.
+    let typo = json.replace("duration_secs", "duration_sec");
+    let err = Policy::from_json(&typo).unwrap_err().to_string();
+    assert!(
+        err.contains("hooks[0]: unknown field `duration_sec`"),
+        "{}",
+        err
+    );
+    let err = Policy::from_json(&json.replace("\"version\"", "\"versoin\": 1, \"version\""))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("versoin: unknown field"), "{}", err);
+
+    let toml = "id = \"p\"\nclock_skew_secs = 60\nsingle_use = false\nversion = 1\n\n[[hooks]]\ntype = \"onlyWithin\"\nperiod = { type = \"duration\", secnds = 5 }\n";
+    let err = Policy::from_toml(toml).unwrap_err().to_string();
+    assert!(err.contains("unknown field `secnds`"), "{}", err);
+
+    // The schema describes every hook type and forbids unknown keys.
+    let schema = Policy::json_schema();
+    assert_eq!(schema["additionalProperties"], serde_json::json!(false));
+    let text = schema.to_string();
+    for kind in [
+        "onlyBefore",
+        "onlyDuring",
+        "blackout",
+        "anyOf",
+        "localRange",
+    ] {
+        assert!(text.contains(kind), "schema is missing {}", kind);
+    }
+}
+
+#[test]
+fn test_lockout_after_failed_attempts() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let pw = Secret::from("pw");
+    let mut store = SecretStore::init_with_clock(&path, &pw, clock_before_2025()).unwrap();
+
+    let opens = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    let mut policy = Policy::new("guarded").add_hook(Hook::OnlyAfter {
+        period: Period::Instant { value: opens },
+    });
+    policy.clock_skew_secs = 0;
+    policy.lockout = Some(Lockout {
+        max_failures: 2,
+        cooldown_secs: 60,
+        backoff: Backoff::Exponential { max_secs: 150 },
+    });
+    store.add_policy(policy).unwrap();
+
+    let mut cred = Credential::new("db".to_string(), SecretType::Password, b"pw".to_vec());
+    cred.policy_ids = vec!["guarded".to_string()];
+    let id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    let early = opens - Duration::hours(1);
+    let verdict =
+        |store: &mut SecretStore, at| store.evaluate_credential(&id, at).unwrap().unwrap().verdict;
+
+    // The first denial is only counted; the second locks for the cooldown.
+    assert_eq!(verdict(&mut store, early), Verdict::NotYetValid);
+    assert_eq!(store.get_credential(&id).unwrap().failed_attempts, 1);
+    assert_eq!(verdict(&mut store, early), Verdict::NotYetValid);
+    let until = early + Duration::seconds(60);
+    assert_eq!(store.get_credential(&id).unwrap().locked_until, Some(until));
+
+    // While locked, access is refused even once the policy would allow it.
+    let eval = store.evaluate_credential(&id, until - Duration::seconds(1));
+    assert!(matches!(
+        eval.unwrap().unwrap().reason,
+        Some(Reason::LockedOut { failures: 2, .. })
+    ));
+    assert_eq!(store.get_credential(&id).unwrap().failed_attempts, 2);
+    // `onlyAfter` is exclusive, so access opens one second after the boundary.
+    let next = store.next_transition(&id, early).unwrap().unwrap();
+    assert_eq!(next.at, opens + Duration::seconds(1));
+
+    // Each further failure doubles the lock, up to the cap.
+    assert_eq!(verdict(&mut store, until), Verdict::NotYetValid);
+    let locked = store.get_credential(&id).unwrap().locked_until.unwrap();
+    assert_eq!(locked, until + Duration::seconds(120));
+    assert_eq!(verdict(&mut store, locked), Verdict::NotYetValid);
+    let capped = store.get_credential(&id).unwrap().locked_until.unwrap();
+    assert_eq!(capped, locked + Duration::seconds(150));
+
+    // Unlocking clears the count and is audited; success also resets it.
+    store.unlock_credential(&id).unwrap();
+    let store_reopened = SecretStore::open(&path, &pw).unwrap();
+    let cred = store_reopened.get_credential(&id).unwrap();
+    assert_eq!((cred.failed_attempts, cred.locked_until), (0, None));
+    let actions: Vec<_> = store_reopened
+        .get_audit_logs()
+        .iter()
+        .filter(|a| a.target_id == id)
+        .map(|a| a.action.as_str())
+        .collect();
+    assert_eq!(
+        actions,
+        ["add", "deny", "deny", "lock", "deny", "deny", "lock", "deny", "lock", "unlock"]
+    );
+
+    assert_eq!(verdict(&mut store, early), Verdict::NotYetValid);
+    assert_eq!(verdict(&mut store, next.at), Verdict::Accept);
+    assert_eq!(store.get_credential(&id).unwrap().failed_attempts, 0);
+
+    // Disabled and rate-limited denials are not counted, so they never lock.
+    let mut paused = Policy::new("paused").add_hook(Hook::RateLimit {
+        max_uses: 1,
+        window_secs: 3_600,
+    });
+    paused.enabled = false;
+    paused.lockout = Some(Lockout {
+        max_failures: 1,
+        cooldown_secs: 60,
+        backoff: Backoff::Fixed,
+    });
+    store.add_policy(paused.clone()).unwrap();
+    let mut cred = Credential::new("api".to_string(), SecretType::Token, b"t".to_vec());
+    cred.policy_ids = vec!["paused".to_string()];
+    let api = cred.id.clone();
+    store.add_credential(cred).unwrap();
+    let verdict = |store: &mut SecretStore| {
+        let now = store.clock().now();
+        store
+            .evaluate_credential(&api, now)
+            .unwrap()
+            .unwrap()
+            .verdict
+    };
+    for _ in 0..3 {
+        assert_eq!(verdict(&mut store), Verdict::Disabled);
+    }
+    paused.enabled = true;
+    store.add_policy(paused).unwrap();
+    assert_eq!(verdict(&mut store), Verdict::Accept);
+    store.increment_usage(&api).unwrap();
+    assert!(matches!(verdict(&mut store), Verdict::RateLimited { .. }));
+    let cred = store.get_credential(&api).unwrap();
+    assert_eq!((cred.failed_attempts, cred.locked_until), (0, None));
+
+    // Cooldowns too long for the calendar are rejected, and never panic.
+    let huge = Lockout {
+        max_failures: 1,
+        cooldown_secs: u64::MAX,
+        backoff: Backoff::Fixed,
+    };
+    assert_eq!(huge.lock_duration(1), Some(Duration::MAX));
+    let mut forever = Policy::new("forever");
+    forever.lockout = Some(huge);
+    let paths: Vec<String> = forever.validate().into_iter().map(|d| d.path).collect();
+    assert_eq!(paths, ["lockout.cooldown_secs"]);
+}
+
+#[test]
+fn test_clock_time_travel() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let pw = Secret::from("pw");
+    let start = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+    let clock = MockClock::new(start);
+    let mut store = SecretStore::init_with_clock(&path, &pw, Arc::new(clock.clone())).unwrap();
+    assert_eq!(store.get_audit_logs()[0].timestamp, start);
+
+    let mut policy = Policy::new("one-hour").add_hook(Hook::OnlyFor {
+        duration_secs: 3600,
+    });
+    policy.clock_skew_secs = 0;
+    store.add_policy(policy).unwrap();
+
+    let mut cred = Credential::new_with_clock(
+        "api".to_string(),
+        SecretType::Token,
+        b"t".to_vec(),
+        store.clock(),
+    );
+    cred.policy_ids = vec!["one-hour".to_string()];
+    assert_eq!(cred.created_at, start);
+    let id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    clock.advance(Duration::minutes(30));
+    let verdict = |store: &mut SecretStore| {
+        let now = store.clock().now();
+        store
+            .evaluate_credential(&id, now)
+            .unwrap()
+            .unwrap()
+            .verdict
+    };
+    assert_eq!(verdict(&mut store), Verdict::Accept);
+    store.increment_usage(&id).unwrap();
+    let used_at = start + Duration::minutes(30);
+    assert_eq!(
+        store.get_credential(&id).unwrap().last_used_at,
+        Some(used_at)
+    );
+    assert_eq!(store.get_audit_logs().last().unwrap().timestamp, used_at);
+
+    // Reopening with the same clock keeps time where the test left it.
+    clock.advance(Duration::hours(1));
+    let mut store = SecretStore::open_with_clock(&path, &pw, Arc::new(clock.clone())).unwrap();
+    assert_eq!(verdict(&mut store), Verdict::Expired);
+    let denied = store.get_audit_logs().last().unwrap();
+    assert_eq!(denied.action, "deny");
+    assert_eq!(denied.timestamp, start + Duration::minutes(90));
+}
+
+#[test]
+fn test_clock_rollback_detection() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let pw = Secret::from("pw");
+    let start = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let clock = MockClock::new(start);
+    let mut store = SecretStore::init_with_clock(&path, &pw, Arc::new(clock.clone())).unwrap();
+    store.add_policy(Policy::new("skewed")).unwrap(); // default skew of 60s
+
+    let mut cred = Credential::new("db".to_string(), SecretType::Password, b"pw".to_vec());
+    cred.policy_ids = vec!["skewed".to_string()];
+    let id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+    let free = Credential::new("free".to_string(), SecretType::Password, b"pw".to_vec());
+    let free_id = free.id.clone();
+    store.add_credential(free).unwrap();
+
+    clock.advance(Duration::hours(2));
+    assert!(store
+        .evaluate_credential(&id, clock.now())
+        .unwrap()
+        .is_some());
+    let high_water = start + Duration::hours(2);
+    assert_eq!(store.time_high_water(), Some(high_water));
+
+    // Turning the clock back within the policy's skew is tolerated.
+    clock.set(high_water - Duration::seconds(30));
+    assert!(store.evaluate_credential(&id, clock.now()).is_ok());
+
+    // Further back, time-sensitive access is refused and the anomaly logged.
+    clock.set(start);
+    let err = store.evaluate_credential(&id, clock.now()).unwrap_err();
+    assert!(matches!(err, Error::ClockRollback { high_water: h, .. } if h == high_water));
+    assert!(store
+        .evaluate_credential(&free_id, clock.now())
+        .unwrap()
+        .is_none());
+    let logged = |store: &SecretStore| {
+        store
+            .get_audit_logs()
+            .iter()
+            .filter(|a| a.action == "clock_rollback")
+            .count()
+    };
+    assert_eq!(logged(&store), 1);
+
+    // The mark is persisted, so reopening notices the rollback straight away.
+    let mut store = SecretStore::open_with_clock(&path, &pw, Arc::new(clock.clone())).unwrap();
+    assert_eq!(store.time_high_water(), Some(high_water));
+    assert_eq!(logged(&store), 2);
+    assert!(matches!(
+        store.evaluate_credential(&id, clock.now()),
+        Err(Error::ClockRollback { .. })
+    ));
+    assert_eq!(logged(&store), 2);
+
+    // Once the clock catches up, access resumes.
+    clock.set(high_water + Duration::minutes(1));
+    assert!(store.evaluate_credential(&id, clock.now()).is_ok());
+
+    // The `now` being evaluated is what is checked, whatever the clock reads,
+    // and a `now` ahead of the clock does not move the mark.
+    assert!(matches!(
+        store.evaluate_credential(&id, start),
+        Err(Error::ClockRollback { .. })
+    ));
+    let ahead = high_water + Duration::days(365);
+    assert!(store.evaluate_credential(&id, ahead).is_ok());
+    assert_eq!(store.time_high_water(), Some(clock.now()));
+
+    // A clock that jumped far ahead once pins the mark there until an admin
+    // resets it, which is audited.
+    let correct = clock.now();
+    clock.set(Utc.with_ymd_and_hms(2099, 1, 1, 0, 0, 0).unwrap());
+    store.evaluate_credential(&id, clock.now()).unwrap();
+    clock.set(correct);
+    assert!(matches!(
+        store.evaluate_credential(&id, clock.now()),
+        Err(Error::ClockRollback { .. })
+    ));
+    let previous = store.reset_time_high_water().unwrap();
+    assert_eq!(previous.map(|t| t.year()), Some(2099));
+    assert_eq!(store.time_high_water(), Some(correct));
+    assert!(store.evaluate_credential(&id, clock.now()).is_ok());
+    let store = SecretStore::open_with_clock(&path, &pw, Arc::new(clock.clone())).unwrap();
+    assert_eq!(store.time_high_water(), Some(correct));
+    let reset = store.get_audit_logs().last().unwrap();
+    assert_eq!(reset.action, "clock_reset");
+    assert!(reset.details.contains("2099"), "{}", reset.details);
+}
+
+/// Answers SNTP requests on `host` as if the time were `server_time`.
+fn sntp_stand_in(host: &str, server_time: chrono::DateTime<Utc>) -> String {
+    let socket = std::net::UdpSocket::bind((host, 0)).unwrap();
+    let addr = socket.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        let secs = (server_time.timestamp() + 2_208_988_800) as u32;
+        let mut buf = [0u8; 48];
+        while let Ok((_, peer)) = socket.recv_from(&mut buf) {
+            let mut response = [0u8; 48];
+            response[0] = 0b00_100_100; // version 4, server mode
+            response[1] = 1; // stratum
+            response[24..32].copy_from_slice(&buf[40..48]);
+            response[32..36].copy_from_slice(&secs.to_be_bytes());
+            response[40..44].copy_from_slice(&secs.to_be_bytes());
+            socket.send_to(&response, peer).unwrap();
+        }
+    });
+    addr
+}
+
+/// Signs the time `server_time` for every nonce it receives on `host`.
+fn signed_time_stand_in(
+    host: &str,
+    key: ed25519_dalek::SigningKey,
+    server_time: chrono::DateTime<Utc>,
+) -> String {
+    use ed25519_dalek::Signer;
+
+    let socket = std::net::UdpSocket::bind((host, 0)).unwrap();
+    let addr = socket.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        let midpoint = server_time.timestamp_micros() as u64;
+        let radius = 500_000u32;
+        let mut nonce = [0u8; 32];
+        while let Ok((_, peer)) = socket.recv_from(&mut nonce) {
+            let message = timesource::signed_time_message(&nonce, midpoint, radius);
+            let mut response = midpoint.to_be_bytes().to_vec();
+            response.extend_from_slice(&radius.to_be_bytes());
+            response.extend_from_slice(&key.sign(&message).to_bytes());
+            socket.send_to(&response, peer).unwrap();
+        }
+    });
+    addr
+}
+
+#[test]
+fn test_trusted_time_sources() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let now = Utc.with_ymd_and_hms(2025, 9, 1, 8, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(now));
+    let mut store =
+        SecretStore::init_with_clock(&path, &Secret::from("pw"), clock.clone()).unwrap();
+
+    let policy = dsl::parse_policy("policy vault: require trusted time and skew 30s").unwrap();
+    assert!(policy.require_trusted_time);
+    assert_eq!(
+        dsl::parse_policy(&dsl::format_policy(&policy)).unwrap(),
+        policy
+    );
+    store.add_policy(policy).unwrap();
+    let mut cred = Credential::new("root".to_string(), SecretType::Key, b"k".to_vec());
+    cred.policy_ids = vec!["vault".to_string()];
+    let id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    // Fails closed without a source, or with one that cannot verify the time.
+    let untrusted = |store: &mut SecretStore| {
+        matches!(
+            store.evaluate_credential(&id, now),
+            Err(Error::UntrustedTime(_))
+        )
+    };
+    assert!(untrusted(&mut store));
+    store.set_time_source(Some(Arc::new(SystemTimeSource::new(clock.clone()))));
+    assert!(untrusted(&mut store));
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let unreachable = SntpTimeSource::new(silent.local_addr().unwrap().to_string())
+        .with_timeout(std::time::Duration::from_millis(200));
+    store.set_time_source(Some(Arc::new(unreachable)));
+    assert!(untrusted(&mut store));
+    assert_eq!(store.get_credential(&id).unwrap().failed_attempts, 0);
+
+    // An SNTP server that agrees with the local clock lets evaluation proceed.
+    let agreeing = SntpTimeSource::new(sntp_stand_in("127.0.0.1", now + Duration::seconds(10)))
+        .with_clock(clock.clone());
+    store.set_time_source(Some(Arc::new(agreeing)));
+    let eval = store.evaluate_credential(&id, now).unwrap().unwrap();
+    assert_eq!(eval.verdict, Verdict::Accept);
+    // Servers are reached over IPv6 as well.
+    let over_ipv6 = SntpTimeSource::new(sntp_stand_in("::1", now)).with_clock(clock.clone());
+    store.set_time_source(Some(Arc::new(over_ipv6)));
+    assert!(store.evaluate_credential(&id, now).unwrap().is_some());
+    let drifted =
+        SntpTimeSource::new(sntp_stand_in("127.0.0.1", now + Duration::hours(1))).with_clock(clock);
+    store.set_time_source(Some(Arc::new(drifted)));
+    assert!(untrusted(&mut store));
+
+    // Signed time is only accepted from the holder of the configured key.
+    let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+    let server = signed_time_stand_in("::1", key.clone(), now);
+    let trusted = SignedTimeSource::new(server.clone(), key.verifying_key());
+    store.set_time_source(Some(Arc::new(trusted)));
+    assert!(store.evaluate_credential(&id, now).unwrap().is_some());
+    let other = ed25519_dalek::SigningKey::from_bytes(&[8u8; 32]).verifying_key();
+    store.set_time_source(Some(Arc::new(SignedTimeSource::new(server, other))));
+    assert!(untrusted(&mut store));
+
+    let denials = store
+        .get_audit_logs()
+        .iter()
+        .filter(|a| a.action == "deny")
+        .count();
+    assert_eq!(denials, 5);
+}
+
+#[test]
+fn test_multiple_and_tag_bound_policies() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let pw = Secret::from("pw");
+    let mut store = SecretStore::init_with_clock(&path, &pw, clock_before_2025()).unwrap();
+
+    // An org-wide baseline for production credentials, plus a per-credential rule.
+    let nine = Utc.with_ymd_and_hms(2025, 3, 3, 9, 0, 0).unwrap(); // a Monday
+    let baseline =
+        dsl::parse_policy("policy baseline: weekdays 09:00-17:00 UTC and skew 0s").unwrap();
+    let own = dsl::parse_policy("policy own: before 2025-03-05T00:00:00Z and skew 0s").unwrap();
+    store.add_policy(baseline).unwrap();
+    store.add_policy(own).unwrap();
+    store.bind_policy("prod", "baseline").unwrap();
+    store.bind_policy("prod", "baseline").unwrap(); // already bound
+
+    let mut cred = Credential::new("db".to_string(), SecretType::Password, b"pw".to_vec());
+    cred.policy_ids = vec!["own".to_string(), "baseline".to_string()];
+    cred.tags = vec!["prod".to_string()];
+    let id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    // Listed policies come first, and each policy applies once.
+    let cred = store.get_credential(&id).unwrap();
+    assert_eq!(
+        store.effective_policies(cred),
+        vec![
+            ("own".to_string(), PolicyBinding::Direct),
+            ("baseline".to_string(), PolicyBinding::Direct),
+        ]
+    );
+    let mut tagged = cred.clone();
+    tagged.policy_ids.truncate(1);
+    assert_eq!(
+        store.effective_policies(&tagged)[1],
+        (
+            "baseline".to_string(),
+            PolicyBinding::Tag("prod".to_string())
+        )
+    );
+    store.add_credential(tagged).unwrap();
+
+    // Both must accept; a denial names the policy that refused.
+    let eval = store.evaluate_credential(&id, nine).unwrap().unwrap();
+    assert_eq!(eval.verdict, Verdict::Accept);
+    let evening = nine + Duration::hours(10);
+    let eval = store.evaluate_credential(&id, evening).unwrap().unwrap();
+    assert!(matches!(eval.verdict, Verdict::PolicyViolation(_)));
+    assert_eq!(eval.details["policy"], "baseline");
+    let eval = store
+        .evaluate_credential(&id, nine + Duration::days(2))
+        .unwrap()
+        .unwrap();
+    assert_eq!(eval.verdict, Verdict::Expired);
+    assert_eq!(eval.details["policy"], "own");
+
+    // Access next changes when either policy does: the baseline closes at
+    // 17:00, and after Tuesday's window the own policy has expired for good.
+    let next = store.next_transition(&id, nine).unwrap().unwrap();
+    assert_eq!(next.at, nine + Duration::hours(8));
+    let next = store.next_transition(&id, evening).unwrap().unwrap();
+    assert_eq!(next.at, nine + Duration::days(1));
+    let last_close = nine + Duration::days(1) + Duration::hours(8);
+    assert!(store.next_transition(&id, last_close).unwrap().is_none());
+
+    // Bound policies can't be removed until unbound.
+    assert!(store.remove_policy("baseline").is_err());
+    store.unbind_policy("prod", "baseline").unwrap();
+    assert!(store.tag_bindings().is_empty());
+    assert!(store.get_audit_logs().iter().any(|a| a.action == "unbind"));
+
+    // Nor can policies a credential lists, and an unknown id fails closed
+    // instead of leaving the credential unrestricted.
+    assert!(store.remove_policy("own").is_err());
+    let mut orphan = Credential::new("orphan".to_string(), SecretType::Token, b"t".to_vec());
+    orphan.policy_ids = vec!["missing".to_string()];
+    let orphan_id = orphan.id.clone();
+    store.add_credential(orphan).unwrap();
+    assert!(matches!(
+        store.evaluate_credential(&orphan_id, nine),
+        Err(Error::InvalidPolicy(_))
+    ));
+
+    // Credentials saved with a single `policy_id` still load.
+    let mut json = serde_json::to_value(store.get_credential(&id).unwrap()).unwrap();
+    let fields = json.as_object_mut().unwrap();
+    fields.remove("policy_ids");
+    fields.insert("policy_id".to_string(), "own".into());
+    let old: Credential = serde_json::from_value(json).unwrap();
+    assert_eq!(old.policy_ids, vec!["own".to_string()]);
+}
+
+#[test]
+fn test_store_default_policy() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let pw = Secret::from("pw");
+    let mut store = SecretStore::init_with_clock(&path, &pw, clock_before_2025()).unwrap();
+
+    let cutoff = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let mut floor = Policy::new("floor").add_hook(Hook::OnlyBefore {
+        period: Period::Instant { value: cutoff },
+    });
+    floor.clock_skew_secs = 0;
+    store.add_policy(floor).unwrap();
+    store.add_policy(Policy::new("own")).unwrap();
+
+    let bare = Credential::new("bare".to_string(), SecretType::Token, b"t".to_vec());
+    let mut owned = Credential::new("owned".to_string(), SecretType::Token, b"t".to_vec());
+    owned.policy_ids = vec!["own".to_string()];
+    let (bare_id, owned_id) = (bare.id.clone(), owned.id.clone());
+    store.add_credential(bare).unwrap();
+    store.add_credential(owned).unwrap();
+
+    let after = cutoff + Duration::days(1);
+    let verdict = |store: &mut SecretStore, id: &str| {
+        store
+            .evaluate_credential(id, after)
+            .unwrap()
+            .map(|e| e.verdict)
+    };
+    assert_eq!(verdict(&mut store, &bare_id), None);
+
+    // A fallback only covers credentials that would otherwise be unrestricted.
+    store
+        .set_default_policy("floor", DefaultMode::Fallback)
+        .unwrap();
+    assert_eq!(verdict(&mut store, &bare_id), Some(Verdict::Expired));
+    assert_eq!(verdict(&mut store, &owned_id), Some(Verdict::Accept));
+    let mut orphan = Credential::new("orphan".to_string(), SecretType::Token, b"t".to_vec());
+    orphan.policy_ids = vec!["missing".to_string()];
+    assert_eq!(
+        store.effective_policies(&orphan).last().unwrap().1,
+        PolicyBinding::StoreDefault
+    );
+
+    // A floor applies to every credential, after its own policies.
+    store
+        .set_default_policy("floor", DefaultMode::Floor)
+        .unwrap();
+    let owned = store.get_credential(&owned_id).unwrap();
+    assert_eq!(
+        store.effective_policies(owned)[1],
+        ("floor".to_string(), PolicyBinding::StoreDefault)
+    );
+    assert_eq!(verdict(&mut store, &owned_id), Some(Verdict::Expired));
+    assert!(store.remove_policy("floor").is_err());
+
+    // The setting is saved with the store, and changes are audited.
+    let mut store = SecretStore::open(&path, &pw).unwrap();
+    assert_eq!(store.default_policy().unwrap().mode, DefaultMode::Floor);
+    store.clear_default_policy().unwrap();
+    assert_eq!(verdict(&mut store, &bare_id), None);
+    let actions: Vec<&str> = store
+        .get_audit_logs()
+        .iter()
+        .map(|a| a.action.as_str())
+        .filter(|a| a.ends_with("_default"))
+        .collect();
+    assert_eq!(actions, ["set_default", "set_default", "clear_default"]);
+}
+
+#[test]
+fn test_expiry_warning_and_grace() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.timely");
+    let mut store =
+        SecretStore::init_with_clock(&path, &Secret::from("pw"), clock_before_2025()).unwrap();
+
+    let cutoff = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let mut policy = Policy::new("rotating").add_hook(Hook::OnlyBefore {
+        period: Period::Instant { value: cutoff },
+    });
+    policy.clock_skew_secs = 0;
+    policy.warn_before_secs = Some(7 * 86_400);
+    policy.grace_period_secs = Some(2 * 86_400);
+    let text = dsl::format_policy(&policy);
+    assert!(text.contains("warn 7d before expiry") && text.contains("grace 2d"));
+    assert_eq!(dsl::parse_policy(&text).unwrap(), policy);
+    store.add_policy(policy).unwrap();
+
+    let mut cred = Credential::new("api".to_string(), SecretType::Token, b"t".to_vec());
+    cred.policy_ids = vec!["rotating".to_string()];
+    let id = cred.id.clone();
+    store.add_credential(cred).unwrap();
+
+    let mut check = |at| {
+        let eval = store.evaluate_credential(&id, at).unwrap().unwrap();
+        (eval.verdict, eval.warning)
+    };
+    assert_eq!(check(cutoff - Duration::days(10)), (Verdict::Accept, None));
+    assert_eq!(
+        check(cutoff - Duration::days(3)),
+        (Verdict::Accept, Some(Warning::ExpiresSoon { at: cutoff }))
+    );
+
+    // After expiry, access continues through the grace period but is audited.
+    let until = cutoff + Duration::days(2);
+    assert_eq!(
+        check(cutoff + Duration::days(1)),
+        (
+            Verdict::Accept,
+            Some(Warning::InGracePeriod {
+                expired_at: cutoff,
+                until
+            })
+        )
+    );
+    assert_eq!(check(until).0, Verdict::Expired);
+
+    let transition = store
+        .next_transition(&id, cutoff + Duration::days(1))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (transition.at, transition.verdict),
+        (until, Verdict::Expired)
+    );
+    let grace: Vec<_> = store
+        .get_audit_logs()
+        .iter()
+        .filter(|a| a.action == "grace")
+        .collect();
+    assert_eq!(grace.len(), 1);
+    assert_eq!(grace[0].target_id, id);
+
+    // The end of a `within` range expires too, also when nested in `anyOf`.
+    let day = |d| Utc.with_ymd_and_hms(2025, 6, d, 12, 0, 0).unwrap();
+    let ctx = |now| EvaluationContext {
+        now,
+        ..Default::default()
+    };
+    let end = Utc.with_ymd_and_hms(2025, 6, 5, 23, 59, 59).unwrap();
+    let mut within = dsl::parse_policy(
+        "within 2025-01-01..2025-06-05 and skew 0s and warn 7d before expiry and grace 2d",
+    )
+    .unwrap();
+    within.hooks = vec![Hook::any_of(within.hooks.clone())];
+    assert_eq!(
+        within.evaluate(&ctx(day(1))).warning,
+        Some(Warning::ExpiresSoon {
+            at: end + Duration::seconds(1)
+        })
+    );
+    let overdue = within.explain(&ctx(day(7)));
+    assert_eq!(overdue.verdict, Verdict::Accept);
+    assert_eq!(
+        overdue.warning,
+        Some(Warning::InGracePeriod {
+            expired_at: end,
+            until: end + Duration::days(2)
+        })
+    );
+    assert_eq!(overdue.matched_hooks, [0]);
+    assert!(overdue.trace.iter().all(|t| t.passed));
+    assert!(matches!(
+        within.evaluate(&ctx(day(9))).verdict,
+        Verdict::PolicyViolation(_)
+    ));
+
+    // Oversized settings are refused rather than overflowing, and a grace
+    // period without anything that expires is flagged.
+    let mut oversized = within.clone();
+    oversized.grace_period_secs = Some(u64::MAX / 4);
+    assert_eq!(oversized.evaluate(&ctx(day(9))).verdict, Verdict::Accept);
+    assert_eq!(oversized.validate()[0].path, "grace_period_secs");
+    let mut pointless = Policy::new("pointless");
+    pointless.grace_period_secs = Some(60);
+    assert_eq!(
+        pointless.validate()[0].severity,
+        timely_pass_sdk::policy::Severity::Warning
+    );
+}